@@ -0,0 +1,108 @@
+//! `#[derive(FromRecord, ToRecord)]` for `sleek_csv`, enabled by its `derive` feature.
+//!
+//! Only structs with named fields are supported. The header name of a field is the
+//! field name, unless given with `#[csv(rename = "name")]`.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Ident, LitStr};
+
+#[proc_macro_derive(FromRecord, attributes(csv))]
+pub fn derive_from_record(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_from_record(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(ToRecord, attributes(csv))]
+pub fn derive_to_record(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_to_record(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+struct Field<'a> {
+    ident: &'a Ident,
+    header: String,
+}
+
+fn fields(input: &DeriveInput) -> syn::Result<Vec<Field<'_>>> {
+    let named = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(named) => named,
+            _ => {
+                let msg = "expected a struct with named fields";
+                return Err(syn::Error::new_spanned(&input.ident, msg));
+            }
+        },
+        _ => return Err(syn::Error::new_spanned(&input.ident, "expected a struct")),
+    };
+    named
+        .named
+        .iter()
+        .map(|field| {
+            let ident = field.ident.as_ref().expect("named fields have names");
+            let mut header = ident.to_string();
+            for attr in field
+                .attrs
+                .iter()
+                .filter(|attr| attr.path().is_ident("csv"))
+            {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("rename") {
+                        header = meta.value()?.parse::<LitStr>()?.value();
+                        Ok(())
+                    } else {
+                        Err(meta.error("unsupported csv attribute"))
+                    }
+                })?;
+            }
+            Ok(Field { ident, header })
+        })
+        .collect()
+}
+
+fn expand_from_record(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = fields(input)?;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let headers = fields.iter().map(|field| &field.header);
+    let idents = fields.iter().map(|field| field.ident);
+    let indices = 0..fields.len();
+    Ok(quote! {
+        impl #impl_generics ::sleek_csv::record::FromRecord for #name #ty_generics #where_clause {
+            const FIELDS: &'static [&'static str] = &[#(#headers),*];
+
+            fn from_record(
+                record: ::sleek_csv::RawRecord<'_>,
+                columns: &::sleek_csv::record::ColumnMap,
+            ) -> ::core::result::Result<Self, ::sleek_csv::ColumnError> {
+                ::core::result::Result::Ok(#name {
+                    #(#idents: columns.parse(record, #indices)?,)*
+                })
+            }
+        }
+    })
+}
+
+fn expand_to_record(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = fields(input)?;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let headers = fields.iter().map(|field| &field.header);
+    let idents = fields.iter().map(|field| field.ident);
+    Ok(quote! {
+        impl #impl_generics ::sleek_csv::record::ToRecord for #name #ty_generics #where_clause {
+            const FIELDS: &'static [&'static str] = &[#(#headers),*];
+
+            fn write_fields(&self, fields: &mut ::sleek_csv::record::FieldWriter<'_>) {
+                #(fields.push(&self.#idents);)*
+            }
+        }
+    })
+}
@@ -0,0 +1,314 @@
+//! Conversion of `ByteRecordArena`s into Apache Arrow `RecordBatch`es.
+//!
+//! The column types can either be inferred from the data or given as an explicit schema.
+//! String and binary columns are built by copying the fields of the column from
+//! the arena's `field_data` into one contiguous Arrow value buffer per column.
+
+use core::convert::TryFrom;
+use core::str;
+use std::sync::Arc;
+
+use arrow_array::builder::{BooleanBuilder, PrimitiveBuilder};
+use arrow_array::types::{
+    ArrowPrimitiveType, Date32Type, Float32Type, Float64Type, Int16Type, Int32Type, Int64Type,
+    Int8Type, UInt16Type, UInt32Type, UInt64Type, UInt8Type,
+};
+use arrow_array::{ArrayRef, BinaryArray, RecordBatch, StringArray};
+use arrow_buffer::{Buffer, NullBufferBuilder, OffsetBuffer, ScalarBuffer};
+use arrow_schema::{ArrowError, DataType, Field, Schema, SchemaRef};
+
+use crate::ByteRecordArena;
+
+/// Infers an Arrow schema for the records in the arena.
+///
+/// Each column is typed as the narrowest of `Boolean`, `Int64`, `Float64`, `Date32`
+/// (`YYYY-MM-DD` or `YYYY/MM/DD`) and `Utf8` that fits all of its non-empty fields.
+/// Columns that contain invalid UTF-8 are typed as `Binary`.
+/// Columns that contain empty fields are marked nullable.
+/// The column names are taken from the headers of the arena, or generated as
+/// `column_1`, `column_2`... if the arena doesn't have headers.
+pub fn infer_schema(arena: &ByteRecordArena) -> Schema {
+    let col_count = column_count(arena);
+    let mut guesses = vec![TypeGuess::new(); col_count];
+    for record in arena.iter() {
+        for (field, guess) in record.iter().zip(guesses.iter_mut()) {
+            guess.update(field);
+        }
+    }
+    let fields: Vec<Field> = guesses
+        .iter()
+        .enumerate()
+        .map(|(i, guess)| Field::new(column_name(arena, i), guess.data_type(), guess.nullable))
+        .collect();
+    Schema::new(fields)
+}
+
+/// Converts the records in the arena into a `RecordBatch` with the given schema.
+///
+/// The schema fields are matched to the arena columns by position.
+/// Empty fields become nulls, except in `Utf8` and `Binary` columns that aren't nullable,
+/// where they become empty values. Missing trailing fields become nulls.
+pub fn to_record_batch(
+    arena: &ByteRecordArena,
+    schema: SchemaRef,
+) -> Result<RecordBatch, ArrowError> {
+    let columns = schema
+        .fields()
+        .iter()
+        .enumerate()
+        .map(|(col, field)| build_column(arena, col, field))
+        .collect::<Result<Vec<_>, _>>()?;
+    RecordBatch::try_new(schema, columns)
+}
+
+fn column_count(arena: &ByteRecordArena) -> usize {
+    if let Some(headers) = arena.headers() {
+        headers.len()
+    } else {
+        arena.iter().map(|r| r.field_count()).max().unwrap_or(0)
+    }
+}
+
+fn column_name(arena: &ByteRecordArena, col: usize) -> String {
+    match arena.headers().and_then(|h| h.iter().nth(col)) {
+        Some(name) => String::from_utf8_lossy(name).into_owned(),
+        None => format!("column_{}", col + 1),
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct TypeGuess {
+    seen: bool,
+    nullable: bool,
+    boolean: bool,
+    int: bool,
+    float: bool,
+    date: bool,
+    utf8: bool,
+}
+
+impl TypeGuess {
+    fn new() -> TypeGuess {
+        TypeGuess {
+            seen: false,
+            nullable: false,
+            boolean: true,
+            int: true,
+            float: true,
+            date: true,
+            utf8: true,
+        }
+    }
+
+    fn update(&mut self, field: &[u8]) {
+        if field.is_empty() {
+            self.nullable = true;
+            return;
+        }
+        self.seen = true;
+        let s = match str::from_utf8(field) {
+            Ok(s) => s,
+            Err(_) => {
+                *self = TypeGuess {
+                    boolean: false,
+                    int: false,
+                    float: false,
+                    date: false,
+                    utf8: false,
+                    ..*self
+                };
+                return;
+            }
+        };
+        self.boolean = self.boolean && s.parse::<bool>().is_ok();
+        self.int = self.int && s.parse::<i64>().is_ok();
+        self.float = self.float && s.parse::<f64>().is_ok();
+        self.date = self.date && parse_date(field).is_some();
+    }
+
+    fn data_type(&self) -> DataType {
+        match *self {
+            TypeGuess { seen: false, .. } => DataType::Utf8,
+            TypeGuess { boolean: true, .. } => DataType::Boolean,
+            TypeGuess { int: true, .. } => DataType::Int64,
+            TypeGuess { float: true, .. } => DataType::Float64,
+            TypeGuess { date: true, .. } => DataType::Date32,
+            TypeGuess { utf8: true, .. } => DataType::Utf8,
+            _ => DataType::Binary,
+        }
+    }
+}
+
+/// Parses `YYYY-MM-DD` or `YYYY/MM/DD` into days since the Unix epoch.
+fn parse_date(field: &[u8]) -> Option<i32> {
+    if field.len() != 10 || field[4] != field[7] || (field[4] != b'-' && field[4] != b'/') {
+        return None;
+    }
+    let num = |digits: &[u8]| -> Option<i64> {
+        digits.iter().try_fold(0, |acc, &d| {
+            if d.is_ascii_digit() {
+                Some(acc * 10 + i64::from(d - b'0'))
+            } else {
+                None
+            }
+        })
+    };
+    let (year, month, day) = (num(&field[0..4])?, num(&field[5..7])?, num(&field[8..10])?);
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let month_len = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return None,
+    };
+    if day < 1 || day > month_len {
+        return None;
+    }
+
+    // Days from civil, see http://howardhinnant.github.io/date_algorithms.html
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    Some((era * 146_097 + doe - 719_468) as i32)
+}
+
+fn parse_error(row: usize, col: usize, field: &[u8], data_type: &DataType) -> ArrowError {
+    ArrowError::ParseError(format!(
+        "row {}, column {}: can't parse {:?} as {}",
+        row,
+        col,
+        String::from_utf8_lossy(field),
+        data_type
+    ))
+}
+
+fn build_column(
+    arena: &ByteRecordArena,
+    col: usize,
+    field: &Field,
+) -> Result<ArrayRef, ArrowError> {
+    let data_type = field.data_type();
+    let array: ArrayRef = match data_type {
+        DataType::Boolean => {
+            let mut builder = BooleanBuilder::with_capacity(arena.record_count() as usize);
+            for (row, record) in arena.iter().enumerate() {
+                match record.get(col) {
+                    None | Some([]) => builder.append_null(),
+                    Some(b"true") => builder.append_value(true),
+                    Some(b"false") => builder.append_value(false),
+                    Some(f) => return Err(parse_error(row, col, f, data_type)),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::Int8 => build_primitive::<Int8Type>(arena, col, data_type)?,
+        DataType::Int16 => build_primitive::<Int16Type>(arena, col, data_type)?,
+        DataType::Int32 => build_primitive::<Int32Type>(arena, col, data_type)?,
+        DataType::Int64 => build_primitive::<Int64Type>(arena, col, data_type)?,
+        DataType::UInt8 => build_primitive::<UInt8Type>(arena, col, data_type)?,
+        DataType::UInt16 => build_primitive::<UInt16Type>(arena, col, data_type)?,
+        DataType::UInt32 => build_primitive::<UInt32Type>(arena, col, data_type)?,
+        DataType::UInt64 => build_primitive::<UInt64Type>(arena, col, data_type)?,
+        DataType::Float32 => build_primitive::<Float32Type>(arena, col, data_type)?,
+        DataType::Float64 => build_primitive::<Float64Type>(arena, col, data_type)?,
+        DataType::Date32 => {
+            let mut builder =
+                PrimitiveBuilder::<Date32Type>::with_capacity(arena.record_count() as usize);
+            for (row, record) in arena.iter().enumerate() {
+                match record.get(col) {
+                    None | Some([]) => builder.append_null(),
+                    Some(f) => builder.append_value(
+                        parse_date(f).ok_or_else(|| parse_error(row, col, f, data_type))?,
+                    ),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::Utf8 => {
+            let (offsets, values, nulls) = build_bytes(arena, col, field.is_nullable())?;
+            Arc::new(StringArray::try_new(offsets, values, nulls)?)
+        }
+        DataType::Binary => {
+            let (offsets, values, nulls) = build_bytes(arena, col, field.is_nullable())?;
+            Arc::new(BinaryArray::try_new(offsets, values, nulls)?)
+        }
+        other => {
+            return Err(ArrowError::NotYetImplemented(format!(
+                "CSV conversion into {}",
+                other
+            )))
+        }
+    };
+    Ok(array)
+}
+
+fn build_primitive<T>(
+    arena: &ByteRecordArena,
+    col: usize,
+    data_type: &DataType,
+) -> Result<ArrayRef, ArrowError>
+where
+    T: ArrowPrimitiveType,
+    T::Native: str::FromStr,
+{
+    let mut builder = PrimitiveBuilder::<T>::with_capacity(arena.record_count() as usize);
+    for (row, record) in arena.iter().enumerate() {
+        match record.get(col) {
+            None | Some([]) => builder.append_null(),
+            Some(f) => {
+                let value = str::from_utf8(f)
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .ok_or_else(|| parse_error(row, col, f, data_type))?;
+                builder.append_value(value);
+            }
+        }
+    }
+    Ok(Arc::new(builder.finish()))
+}
+
+type BytesParts = (OffsetBuffer<i32>, Buffer, Option<arrow_buffer::NullBuffer>);
+
+/// Copies the fields of a column into a single contiguous value buffer.
+fn build_bytes(
+    arena: &ByteRecordArena,
+    col: usize,
+    nullable: bool,
+) -> Result<BytesParts, ArrowError> {
+    let row_count = arena.record_count() as usize;
+    let mut offsets = Vec::with_capacity(row_count + 1);
+    let mut values = Vec::new();
+    let mut nulls = NullBufferBuilder::new(row_count);
+    offsets.push(0i32);
+    for record in arena.iter() {
+        let field = record.get(col);
+        match field {
+            None | Some([]) if nullable => nulls.append_null(),
+            _ => nulls.append_non_null(),
+        }
+        values.extend_from_slice(field.unwrap_or(&[]));
+        let offset = i32::try_from(values.len())
+            .map_err(|_| ArrowError::ComputeError("column data exceeds 2 GiB".into()))?;
+        offsets.push(offset);
+    }
+    Ok((
+        OffsetBuffer::new(ScalarBuffer::from(offsets)),
+        Buffer::from_vec(values),
+        nulls.finish(),
+    ))
+}
+
+#[test]
+fn test_parse_date() {
+    assert_eq!(parse_date(b"1970-01-01"), Some(0));
+    assert_eq!(parse_date(b"1970/01/02"), Some(1));
+    assert_eq!(parse_date(b"2000-03-01"), Some(11017));
+    assert_eq!(parse_date(b"1969-12-31"), Some(-1));
+    assert_eq!(parse_date(b"2019-02-29"), None);
+    assert_eq!(parse_date(b"2019-02/28"), None);
+    assert_eq!(parse_date(b"2019/10/01 1:11:11"), None);
+}
@@ -0,0 +1,118 @@
+//! Boolean vocabularies such as `Y`/`N`, `1`/`0` or `on`/`off`.
+
+use std::collections::HashMap;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The values that stand for `true` and `false`.
+///
+/// The default format accepts only `true` and `false`, like `str::parse`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BoolFormat {
+    pub true_values: Vec<Vec<u8>>,
+    pub false_values: Vec<Vec<u8>>,
+    /// Compares the values ignoring ASCII case.
+    pub ignore_case: bool,
+}
+
+impl Default for BoolFormat {
+    fn default() -> Self {
+        BoolFormat {
+            true_values: vec![b"true".to_vec()],
+            false_values: vec![b"false".to_vec()],
+            ignore_case: false,
+        }
+    }
+}
+
+impl BoolFormat {
+    pub fn new<I, J>(true_values: I, false_values: J, ignore_case: bool) -> BoolFormat
+    where
+        I: IntoIterator,
+        I::Item: AsRef<[u8]>,
+        J: IntoIterator,
+        J::Item: AsRef<[u8]>,
+    {
+        BoolFormat {
+            true_values: true_values
+                .into_iter()
+                .map(|value| value.as_ref().to_vec())
+                .collect(),
+            false_values: false_values
+                .into_iter()
+                .map(|value| value.as_ref().to_vec())
+                .collect(),
+            ignore_case,
+        }
+    }
+
+    /// Accepts `true`/`false`, `t`/`f`, `yes`/`no`, `y`/`n`, `on`/`off` and `1`/`0`,
+    /// ignoring case.
+    pub fn lenient() -> BoolFormat {
+        BoolFormat::new(
+            &["true", "t", "yes", "y", "on", "1"],
+            &["false", "f", "no", "n", "off", "0"],
+            true,
+        )
+    }
+
+    pub fn parse(&self, field: &[u8]) -> Option<bool> {
+        let matches = |value: &Vec<u8>| {
+            if self.ignore_case {
+                value.eq_ignore_ascii_case(field)
+            } else {
+                value == field
+            }
+        };
+        if self.true_values.iter().any(matches) {
+            Some(true)
+        } else if self.false_values.iter().any(matches) {
+            Some(false)
+        } else {
+            None
+        }
+    }
+}
+
+/// A default `BoolFormat` and the formats of specific columns, by header name.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BoolFormats {
+    pub default: BoolFormat,
+    columns: HashMap<Vec<u8>, BoolFormat>,
+}
+
+impl BoolFormats {
+    pub fn new(default: BoolFormat) -> BoolFormats {
+        BoolFormats {
+            default,
+            columns: HashMap::new(),
+        }
+    }
+
+    /// Sets the format of the column with the given header name.
+    pub fn set_column<N: AsRef<[u8]>>(&mut self, name: N, format: BoolFormat) {
+        self.columns.insert(name.as_ref().to_vec(), format);
+    }
+
+    /// Returns the format of the named column, or the default format.
+    pub fn format(&self, name: Option<&[u8]>) -> &BoolFormat {
+        name.and_then(|name| self.columns.get(name))
+            .unwrap_or(&self.default)
+    }
+}
+
+#[test]
+fn test_parse() {
+    let lenient = BoolFormat::lenient();
+    assert_eq!(lenient.parse(b"Y"), Some(true));
+    assert_eq!(lenient.parse(b"OFF"), Some(false));
+    assert_eq!(lenient.parse(b"2"), None);
+    assert_eq!(BoolFormat::default().parse(b"True"), None);
+
+    let mut formats = BoolFormats::default();
+    formats.set_column("active", BoolFormat::new(&["J"], &["N"], false));
+    assert_eq!(formats.format(Some(b"active")).parse(b"J"), Some(true));
+    assert_eq!(formats.format(None).parse(b"J"), None);
+}
@@ -1,40 +1,77 @@
 extern crate alloc;
 
 #[cfg(feature = "serde")]
-use serde::Deserialize;
+use serde::de::{Deserialize, DeserializeSeed};
 
+#[cfg(feature = "serde")]
+use alloc::borrow::Cow;
 use core::fmt;
+#[cfg(feature = "serde")]
+use core::marker::PhantomData;
 use core::ops::Not;
 
-use crate::raw::{RawRecord, RawRecordArena, RawRecordsIter};
+#[cfg(feature = "arrow")]
+use crate::arrow;
+use crate::column::{self, ColumnError, ColumnOptions, FromStrBytes};
+use crate::convert::{self, ConvertError, Converters};
+#[cfg(feature = "chrono")]
+use crate::datetime::{self, DateTimeField};
 #[cfg(feature = "serde")]
-use crate::{deserialize, error};
+use crate::deserialize::{self, DeserializeOptions};
+#[cfg(feature = "serde")]
+use crate::error;
+use crate::raw::{RawRecord, RawRecordArena, RawRecordsIter};
+use crate::record::{self, FieldWriter, FromRecord, RecordError, ToRecord};
+use crate::schema::Schema;
+use crate::value::ValueRowsIter;
 use crate::{Headers, Position};
 
 pub struct ByteRecordArena {
     pub(crate) inner: RawRecordArena,
     pub(crate) start_pos: Option<Position>,
     pub(crate) headers_inner: Option<Headers>,
+    pub(crate) trailer_inner: Option<RawRecordArena>,
     pub(crate) bytes_init: usize,
 }
 
 pub struct ByteRecordsIter<'a>(RawRecordsIter<'a>);
 
+/// A lazy iterator deserializing the full records of an arena,
+/// returned by `ByteRecordArena::deserialize_iter`.
+#[cfg(feature = "serde")]
+pub struct DeserializeIter<'a, D> {
+    records: RawRecordsIter<'a>,
+    headers: Option<RawRecord<'a>>,
+    options: Cow<'a, DeserializeOptions>,
+    index: usize,
+    _output: PhantomData<fn() -> D>,
+}
+
+impl Default for ByteRecordArena {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ByteRecordArena {
     pub fn new() -> ByteRecordArena {
         ByteRecordArena {
             inner: RawRecordArena::new(),
             start_pos: None,
             headers_inner: None,
+            trailer_inner: None,
             bytes_init: 0,
         }
     }
 
+    /// Creates an arena with known headers, e.g. for reading a headerless file
+    /// with a `Reader` that doesn't read headers. The headers set the expected column count.
     pub fn with_headers(headers: Headers) -> ByteRecordArena {
         ByteRecordArena {
             inner: RawRecordArena::new(),
             start_pos: None,
             headers_inner: Some(headers),
+            trailer_inner: None,
             bytes_init: 0,
         }
     }
@@ -55,6 +92,7 @@ impl ByteRecordArena {
     /// i.e. it doesn't contain any input information. This includes:
     /// 1) doesn't contain any header information
     /// 2) doesn't contain any records
+    ///
     /// However, it doesn't mean that the arena is in a "freshly initialized" state;
     /// it might contain a non-zero starting position or headers.
     pub fn is_empty(&self) -> bool {
@@ -68,6 +106,7 @@ impl ByteRecordArena {
     /// 2) doesn't contain any records
     /// 3) doesn't contain partial records
     /// 4) doesn't have starting position other than 0.
+    ///
     /// However, it doesn't take into account some purely internal properties that have only
     /// diminishingly small performance effects. These properties include the internal capacity
     /// of the storage fields and the info whether they have been zeroed or contain undefined bytes.
@@ -95,6 +134,7 @@ impl ByteRecordArena {
     /// TODO: decide what to do with headers and start up position
     pub fn clear(&mut self) {
         self.start_pos = None;
+        self.trailer_inner = None;
         self.inner.clear();
     }
 
@@ -106,6 +146,14 @@ impl ByteRecordArena {
         }
     }
 
+    /// Returns the trailer record, if the input ended with one.
+    /// See `Reader::set_trailer_marker` and `Reader::finish`.
+    pub fn trailer(&self) -> Option<RawRecord<'_>> {
+        self.trailer_inner
+            .as_ref()
+            .and_then(|trailer| trailer.iter().next())
+    }
+
     pub fn start_pos(&self) -> Option<&Position> {
         self.start_pos.as_ref()
     }
@@ -147,6 +195,28 @@ impl ByteRecordArena {
             .push((self.inner.field_data.len(), self.inner.field_ends.len()));
     }
 
+    /// Appends a field to the current record. Finish the record with `terminate_record`.
+    /// Not to be mixed with `expose_data` and `terminate_field` within a record.
+    pub fn push_field(&mut self, field: &[u8]) {
+        self.push_field_with(|data| data.extend_from_slice(field));
+    }
+
+    pub(crate) fn push_field_with(&mut self, write: impl FnOnce(&mut Vec<u8>)) {
+        let &(last_record_end_field_data, _) = self.inner.record_ends.last().unwrap_or(&(0, 0));
+        write(&mut self.inner.field_data);
+        let data_len = self.inner.field_data.len();
+        self.bytes_init = self.bytes_init.max(data_len);
+        self.inner
+            .field_ends
+            .push(data_len - last_record_end_field_data);
+    }
+
+    /// Appends the value as a full record.
+    pub fn push_record<T: ToRecord + ?Sized>(&mut self, record: &T) {
+        record.write_fields(&mut FieldWriter { arena: self });
+        self.terminate_record();
+    }
+
     pub fn reserve_space(&mut self, data: usize) {
         let old_len = self.inner.field_data.len();
         // Not only reserve, but also ensure that the buffer is initialized
@@ -159,12 +229,180 @@ impl ByteRecordArena {
         ByteRecordsIter(self.inner.iter())
     }
 
+    /// Parses the fields of the n:th column of all full records.
+    /// Use `Option<T>` to map empty fields to `None`.
+    pub fn parse_column<T: FromStrBytes>(&self, column: usize) -> Result<Vec<T>, ColumnError> {
+        column::parse_column(self, column, &ColumnOptions::default())
+    }
+
+    /// Like `parse_column`, but with null tokens, a number format and boolean formats.
+    pub fn parse_column_with<T: FromStrBytes>(
+        &self,
+        column: usize,
+        options: &ColumnOptions,
+    ) -> Result<Vec<T>, ColumnError> {
+        column::parse_column(self, column, options)
+    }
+
+    /// Returns a copy of the arena with the fields of the columns that have a converter
+    /// replaced by the converted fields. The headers and the trailer are kept.
+    pub fn map_columns(&self, converters: &Converters) -> Result<ByteRecordArena, ConvertError> {
+        convert::map_columns(self, converters)
+    }
+
+    /// Converts the fields of the n:th column of all full records into values.
+    pub fn map_column<T, E: fmt::Display>(
+        &self,
+        column: usize,
+        convert: impl Fn(&[u8]) -> Result<T, E>,
+    ) -> Result<Vec<T>, ConvertError> {
+        let mut output = Vec::with_capacity(self.record_count() as usize);
+        for (row, record) in self.iter().enumerate() {
+            let value = match record.get(column) {
+                Some(field) => convert(field).map_err(|err| err.to_string()),
+                None => Err("missing field".to_string()),
+            };
+            output.push(value.map_err(|message| ConvertError {
+                row,
+                column,
+                message,
+            })?);
+        }
+        Ok(output)
+    }
+
+    /// Decodes the full records. The columns of the fields are looked up by header name
+    /// once, or taken in order if the arena has no headers.
+    pub fn decode_records<T: FromRecord>(&self) -> Result<Vec<T>, RecordError> {
+        record::decode_records(self)
+    }
+
+    /// Returns the full records as rows of untyped values, guessing the type of each field.
+    pub fn rows_as_values(&self) -> ValueRowsIter<'_> {
+        ValueRowsIter::new(self.inner.iter(), None)
+    }
+
+    /// Like `rows_as_values`, but parses the fields as the column types of the schema,
+    /// e.g. one inferred by `SchemaInferrer`.
+    pub fn rows_as_values_with(&self, schema: &Schema) -> ValueRowsIter<'_> {
+        ValueRowsIter::new(self.inner.iter(), Some(schema))
+    }
+
+    /// Parses the dates or times of the n:th column of all full records with the given format.
+    /// Use `Option<T>` to map empty fields to `None`.
+    #[cfg(feature = "chrono")]
+    pub fn parse_datetime_column<T: DateTimeField>(
+        &self,
+        column: usize,
+        format: &str,
+    ) -> Result<Vec<T>, ColumnError> {
+        datetime::parse_column(self, column, format)
+    }
+
+    /// Deserializes the full records into `output`, by position. To deserialize structs and
+    /// maps by header name, set `DeserializeOptions::match_headers` and use `deserialize_with`.
     #[cfg(feature = "serde")]
     pub fn deserialize<'de, D: Deserialize<'de>>(
         &'de self,
         output: &mut Vec<D>,
     ) -> Result<usize, error::Error> {
-        deserialize::deserialize_byte_record_arena(&self.inner, None, output)
+        self.deserialize_with(&DeserializeOptions::default(), output)
+    }
+
+    /// Deserializes the full records into `output` with the given options.
+    ///
+    /// The columns collected by a `#[serde(flatten)]` field are buffered with their types
+    /// inferred, so a flattened `HashMap<String, String>` fails on numeric or empty leftover
+    /// fields unless `DeserializeOptions::buffer_as_strings` is set.
+    #[cfg(feature = "serde")]
+    pub fn deserialize_with<'de, D: Deserialize<'de>>(
+        &'de self,
+        options: &DeserializeOptions,
+        output: &mut Vec<D>,
+    ) -> Result<usize, error::Error> {
+        let headers = self.headers().map(|headers| headers.names());
+        deserialize::deserialize_byte_record_arena(&self.inner, headers, options, output)
+    }
+
+    /// Like `deserialize`, but each record is deserialized with the seed `&mut *context`,
+    /// which can hold state shared by the records, e.g. a table for interning strings.
+    /// Implement `DeserializeSeed` for `&mut` the context type to use it.
+    #[cfg(feature = "serde")]
+    pub fn deserialize_seed<'de, C, V>(
+        &'de self,
+        context: &mut C,
+        output: &mut Vec<V>,
+    ) -> Result<usize, error::Error>
+    where
+        for<'c> &'c mut C: DeserializeSeed<'de, Value = V>,
+    {
+        self.deserialize_seed_with(&DeserializeOptions::default(), context, output)
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn deserialize_seed_with<'de, C, V>(
+        &'de self,
+        options: &DeserializeOptions,
+        context: &mut C,
+        output: &mut Vec<V>,
+    ) -> Result<usize, error::Error>
+    where
+        for<'c> &'c mut C: DeserializeSeed<'de, Value = V>,
+    {
+        let headers = self.headers().map(|headers| headers.names());
+        deserialize::deserialize_byte_record_arena_seed(
+            &self.inner,
+            headers,
+            options,
+            context,
+            output,
+        )
+    }
+
+    /// Like `deserialize`, but deserializes the records one by one as the iterator
+    /// is advanced, without collecting them.
+    #[cfg(feature = "serde")]
+    pub fn deserialize_iter<'de, D: Deserialize<'de>>(&'de self) -> DeserializeIter<'de, D> {
+        self.deserialize_iter_options(Cow::Owned(DeserializeOptions::default()))
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn deserialize_iter_with<'de, D: Deserialize<'de>>(
+        &'de self,
+        options: &'de DeserializeOptions,
+    ) -> DeserializeIter<'de, D> {
+        self.deserialize_iter_options(Cow::Borrowed(options))
+    }
+
+    #[cfg(feature = "serde")]
+    fn deserialize_iter_options<'de, D: Deserialize<'de>>(
+        &'de self,
+        options: Cow<'de, DeserializeOptions>,
+    ) -> DeserializeIter<'de, D> {
+        DeserializeIter {
+            records: self.inner.iter(),
+            headers: self.headers().map(|headers| headers.names()),
+            options,
+            index: 0,
+            _output: PhantomData,
+        }
+    }
+
+    /// Converts the records into an Arrow `RecordBatch`, inferring the schema from the data.
+    /// See `arrow::infer_schema` for the inference rules.
+    #[cfg(feature = "arrow")]
+    pub fn to_record_batch(&self) -> Result<arrow_array::RecordBatch, arrow_schema::ArrowError> {
+        let schema = arrow::infer_schema(self);
+        arrow::to_record_batch(self, std::sync::Arc::new(schema))
+    }
+
+    /// Converts the records into an Arrow `RecordBatch` with an explicit schema.
+    #[cfg(feature = "arrow")]
+    pub fn to_record_batch_with_schema(
+        &self,
+        schema: arrow_schema::SchemaRef,
+    ) -> Result<arrow_array::RecordBatch, arrow_schema::ArrowError> {
+        arrow::to_record_batch(self, schema)
     }
 
     pub fn complete_partial(&mut self) {
@@ -179,6 +417,22 @@ impl<'a> Iterator for ByteRecordsIter<'a> {
     }
 }
 
+#[cfg(feature = "serde")]
+impl<'a, D: Deserialize<'a>> Iterator for DeserializeIter<'a, D> {
+    type Item = Result<D, error::Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let record = self.records.next()?;
+        let index = self.index;
+        self.index += 1;
+        Some(deserialize::deserialize_raw_record(
+            record,
+            index,
+            self.headers,
+            &self.options,
+        ))
+    }
+}
+
 impl fmt::Debug for ByteRecordArena {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
         fmt::Debug::fmt(&self.inner, f)
@@ -0,0 +1,327 @@
+//! Typed column extraction without Serde.
+//!
+//! `FromStrBytes` parses values straight from the raw field bytes, without
+//! UTF-8 validating them first. Integers accept the same `0x` hex prefix as the
+//! Serde deserializer does. `Option<T>` maps empty fields, and optionally the
+//! fields matching `NullTokens`, to `None`. Booleans are parsed with the `BoolFormat`
+//! of the column.
+
+use core::fmt;
+use core::str;
+use std::error;
+
+use crate::{BoolFormat, BoolFormats, ByteRecordArena, NullTokens, NumberFormat};
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ParseError {
+    /// The field is empty, but the type doesn't accept empty values.
+    Empty,
+    /// The field contains a byte that isn't valid for the type.
+    InvalidDigit,
+    /// The number doesn't fit in the type.
+    Overflow,
+    /// The field isn't a valid float.
+    InvalidFloat,
+    /// The field isn't one of the values of the `BoolFormat`.
+    InvalidBool,
+    /// The field isn't valid UTF-8.
+    InvalidUtf8,
+    /// The field doesn't match the date or time format.
+    InvalidDateTime,
+}
+
+impl error::Error for ParseError {}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match self {
+            ParseError::Empty => "cannot parse value from empty field",
+            ParseError::InvalidDigit => "invalid digit found in field",
+            ParseError::Overflow => "number too large or too small to fit in target type",
+            ParseError::InvalidFloat => "invalid float literal",
+            ParseError::InvalidBool => "invalid boolean",
+            ParseError::InvalidUtf8 => "invalid UTF-8",
+            ParseError::InvalidDateTime => "date or time doesn't match the format",
+        };
+        f.write_str(msg)
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ColumnErrorKind {
+    /// The record doesn't have a field at the column index.
+    MissingField,
+    Parse(ParseError),
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+/// 0-based rows and columns, rows not including header.
+pub struct ColumnError {
+    pub row: usize,
+    pub column: usize,
+    pub kind: ColumnErrorKind,
+}
+
+impl error::Error for ColumnError {}
+
+impl fmt::Display for ColumnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Column extraction failed on row {}, column {} (0-based, header not in count): ",
+            self.row, self.column
+        )?;
+        match self.kind {
+            ColumnErrorKind::MissingField => write!(f, "missing field"),
+            ColumnErrorKind::Parse(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+/// Parsing a value from the raw bytes of a field.
+pub trait FromStrBytes: Sized {
+    fn from_str_bytes(bytes: &[u8]) -> Result<Self, ParseError>;
+
+    /// Like `from_str_bytes`, but numbers and booleans are expected in the given formats.
+    /// Other types ignore the formats.
+    fn from_str_bytes_with_format(
+        bytes: &[u8],
+        number_format: &NumberFormat,
+        bool_format: &BoolFormat,
+    ) -> Result<Self, ParseError> {
+        let _ = (number_format, bool_format);
+        Self::from_str_bytes(bytes)
+    }
+}
+
+/// Options for `ByteRecordArena::parse_column_with`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ColumnOptions {
+    /// Fields matching the null tokens are parsed as empty fields.
+    pub null_tokens: NullTokens,
+    pub number_format: NumberFormat,
+    /// The boolean formats, by header name if the arena has headers.
+    pub bool_formats: BoolFormats,
+}
+
+pub(crate) fn parse_column<T: FromStrBytes>(
+    arena: &ByteRecordArena,
+    column: usize,
+    options: &ColumnOptions,
+) -> Result<Vec<T>, ColumnError> {
+    let name = arena
+        .headers()
+        .filter(|headers| column < headers.len())
+        .map(|headers| headers.get(column));
+    let bool_format = options.bool_formats.format(name);
+    parse_column_by(arena, column, |field| {
+        let field = options.null_tokens.normalize(field);
+        T::from_str_bytes_with_format(field, &options.number_format, bool_format)
+    })
+}
+
+pub(crate) fn parse_column_by<T>(
+    arena: &ByteRecordArena,
+    column: usize,
+    parse: impl Fn(&[u8]) -> Result<T, ParseError>,
+) -> Result<Vec<T>, ColumnError> {
+    let mut output = Vec::with_capacity(arena.record_count() as usize);
+    for (row, record) in arena.iter().enumerate() {
+        let field = record.get(column).ok_or(ColumnError {
+            row,
+            column,
+            kind: ColumnErrorKind::MissingField,
+        })?;
+        output.push(parse(field).map_err(|err| ColumnError {
+            row,
+            column,
+            kind: ColumnErrorKind::Parse(err),
+        })?);
+    }
+    Ok(output)
+}
+
+fn digit_value(byte: u8, radix: u8) -> Option<u8> {
+    let value = match byte {
+        b'0'..=b'9' => byte - b'0',
+        b'a'..=b'f' => byte - b'a' + 10,
+        b'A'..=b'F' => byte - b'A' + 10,
+        _ => return None,
+    };
+    if value < radix {
+        Some(value)
+    } else {
+        None
+    }
+}
+
+macro_rules! impl_from_str_bytes_int {
+    ($($inttype:ty),*) => {$(
+        impl FromStrBytes for $inttype {
+            fn from_str_bytes(bytes: &[u8]) -> Result<$inttype, ParseError> {
+                let (radix, bytes) = match bytes.strip_prefix(b"0x") {
+                    Some(hex) => (16, hex),
+                    None => (10, bytes),
+                };
+                let (negative, digits) = match bytes {
+                    [b'-', rest @ ..] => (true, rest),
+                    [b'+', rest @ ..] => (false, rest),
+                    _ => (false, bytes),
+                };
+                if bytes.is_empty() {
+                    return Err(ParseError::Empty);
+                }
+                if digits.is_empty() || (negative && <$inttype>::MIN == 0) {
+                    return Err(ParseError::InvalidDigit);
+                }
+                let mut acc: $inttype = 0;
+                for &byte in digits {
+                    let digit = digit_value(byte, radix).ok_or(ParseError::InvalidDigit)?;
+                    acc = acc
+                        .checked_mul(radix as $inttype)
+                        .and_then(|acc| {
+                            if negative {
+                                acc.checked_sub(digit as $inttype)
+                            } else {
+                                acc.checked_add(digit as $inttype)
+                            }
+                        })
+                        .ok_or(ParseError::Overflow)?;
+                }
+                Ok(acc)
+            }
+
+            fn from_str_bytes_with_format(
+                bytes: &[u8],
+                number_format: &NumberFormat,
+                _bool_format: &BoolFormat,
+            ) -> Result<$inttype, ParseError> {
+                Self::from_str_bytes(&number_format.normalize(bytes))
+            }
+        }
+    )*}
+}
+
+impl_from_str_bytes_int!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+macro_rules! impl_from_str_bytes_float {
+    ($($floattype:ty),*) => {$(
+        impl FromStrBytes for $floattype {
+            fn from_str_bytes(bytes: &[u8]) -> Result<$floattype, ParseError> {
+                if bytes.is_empty() {
+                    return Err(ParseError::Empty);
+                }
+                if !bytes.is_ascii() {
+                    return Err(ParseError::InvalidFloat);
+                }
+                // This is safe because ASCII is always valid UTF-8
+                let s = unsafe { str::from_utf8_unchecked(bytes) };
+                s.parse().map_err(|_| ParseError::InvalidFloat)
+            }
+
+            fn from_str_bytes_with_format(
+                bytes: &[u8],
+                number_format: &NumberFormat,
+                _bool_format: &BoolFormat,
+            ) -> Result<$floattype, ParseError> {
+                Self::from_str_bytes(&number_format.normalize(bytes))
+            }
+        }
+    )*}
+}
+
+impl_from_str_bytes_float!(f32, f64);
+
+impl FromStrBytes for bool {
+    fn from_str_bytes(bytes: &[u8]) -> Result<bool, ParseError> {
+        match bytes {
+            b"true" => Ok(true),
+            b"false" => Ok(false),
+            b"" => Err(ParseError::Empty),
+            _ => Err(ParseError::InvalidBool),
+        }
+    }
+
+    fn from_str_bytes_with_format(
+        bytes: &[u8],
+        _number_format: &NumberFormat,
+        bool_format: &BoolFormat,
+    ) -> Result<bool, ParseError> {
+        if bytes.is_empty() {
+            return Err(ParseError::Empty);
+        }
+        bool_format.parse(bytes).ok_or(ParseError::InvalidBool)
+    }
+}
+
+impl FromStrBytes for String {
+    fn from_str_bytes(bytes: &[u8]) -> Result<String, ParseError> {
+        String::from_utf8(bytes.to_vec()).map_err(|_| ParseError::InvalidUtf8)
+    }
+}
+
+impl FromStrBytes for Vec<u8> {
+    fn from_str_bytes(bytes: &[u8]) -> Result<Vec<u8>, ParseError> {
+        Ok(bytes.to_vec())
+    }
+}
+
+impl<T: FromStrBytes> FromStrBytes for Option<T> {
+    fn from_str_bytes(bytes: &[u8]) -> Result<Option<T>, ParseError> {
+        if bytes.is_empty() {
+            Ok(None)
+        } else {
+            T::from_str_bytes(bytes).map(Some)
+        }
+    }
+
+    fn from_str_bytes_with_format(
+        bytes: &[u8],
+        number_format: &NumberFormat,
+        bool_format: &BoolFormat,
+    ) -> Result<Option<T>, ParseError> {
+        if bytes.is_empty() {
+            Ok(None)
+        } else {
+            T::from_str_bytes_with_format(bytes, number_format, bool_format).map(Some)
+        }
+    }
+}
+
+#[test]
+fn test_from_str_bytes_int() {
+    assert_eq!(u8::from_str_bytes(b"255"), Ok(255));
+    assert_eq!(u8::from_str_bytes(b"256"), Err(ParseError::Overflow));
+    assert_eq!(u8::from_str_bytes(b"-1"), Err(ParseError::InvalidDigit));
+    assert_eq!(i8::from_str_bytes(b"-128"), Ok(-128));
+    assert_eq!(i8::from_str_bytes(b"+127"), Ok(127));
+    assert_eq!(i8::from_str_bytes(b"-129"), Err(ParseError::Overflow));
+    assert_eq!(i32::from_str_bytes(b"0x7F"), Ok(0x7F));
+    assert_eq!(u32::from_str_bytes(b"0xa9"), Ok(0xA9));
+    assert_eq!(u32::from_str_bytes(b"7F"), Err(ParseError::InvalidDigit));
+    assert_eq!(i64::from_str_bytes(b""), Err(ParseError::Empty));
+    assert_eq!(i64::from_str_bytes(b"-"), Err(ParseError::InvalidDigit));
+    assert_eq!(i64::from_str_bytes(b" 1"), Err(ParseError::InvalidDigit));
+    assert_eq!(
+        i128::from_str_bytes(b"-170141183460469231731687303715884105728"),
+        Ok(i128::MIN)
+    );
+}
+
+#[test]
+fn test_from_str_bytes_other() {
+    assert_eq!(f64::from_str_bytes(b"1.5e3"), Ok(1500.0));
+    assert_eq!(f64::from_str_bytes(b"1,5"), Err(ParseError::InvalidFloat));
+    assert_eq!(
+        f32::from_str_bytes("１".as_bytes()),
+        Err(ParseError::InvalidFloat)
+    );
+    assert_eq!(bool::from_str_bytes(b"false"), Ok(false));
+    assert_eq!(bool::from_str_bytes(b"False"), Err(ParseError::InvalidBool));
+    assert_eq!(Option::<u16>::from_str_bytes(b""), Ok(None));
+    assert_eq!(Option::<u16>::from_str_bytes(b"7"), Ok(Some(7)));
+    assert_eq!(
+        String::from_str_bytes(b"\xff"),
+        Err(ParseError::InvalidUtf8)
+    );
+}
@@ -0,0 +1,173 @@
+//! Custom per-column conversions, such as normalizing phone numbers or fixing code pages.
+//!
+//! Converters map the raw bytes of a field into new bytes. They are applied with
+//! `ByteRecordArena::map_columns`, which produces a transformed arena, and by the Serde
+//! deserializer to the fields deserialized as strings, if set in `DeserializeOptions`.
+
+use alloc::borrow::Cow;
+use alloc::sync::Arc;
+use core::fmt;
+use std::collections::HashMap;
+use std::error;
+
+use crate::raw::RawRecordArena;
+use crate::ByteRecordArena;
+
+/// A conversion function. The error is converted into a message.
+pub(crate) type Converter = Arc<dyn Fn(&[u8]) -> Result<Cow<'_, [u8]>, String> + Send + Sync>;
+
+/// The failure of a converter, with 0-based rows and columns, rows not including header.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ConvertError {
+    pub row: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl error::Error for ConvertError {}
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Conversion failed on row {}, column {} (0-based, header not in count): {}",
+            self.row, self.column, self.message
+        )
+    }
+}
+
+/// A registry of converters by column index or header name.
+/// A converter set by index takes precedence over one set by name.
+///
+/// Converters are shared when cloned, and registries compare equal
+/// if they hold the same converters for the same columns.
+#[derive(Clone, Default)]
+pub struct Converters {
+    by_index: HashMap<usize, Converter>,
+    by_name: HashMap<Vec<u8>, Converter>,
+}
+
+fn erase<F>(convert: F) -> Converter
+where
+    F: Fn(&[u8]) -> Result<Cow<'_, [u8]>, String> + Send + Sync + 'static,
+{
+    Arc::new(convert)
+}
+
+fn converter<F, E>(convert: F) -> Converter
+where
+    F: Fn(&[u8]) -> Result<Cow<'_, [u8]>, E> + Send + Sync + 'static,
+    E: fmt::Display,
+{
+    erase(move |field: &[u8]| convert(field).map_err(|err| err.to_string()))
+}
+
+impl Converters {
+    pub fn new() -> Converters {
+        Converters::default()
+    }
+
+    /// Sets the converter of the 0-based column.
+    pub fn set_column<F, E>(&mut self, column: usize, convert: F)
+    where
+        F: Fn(&[u8]) -> Result<Cow<'_, [u8]>, E> + Send + Sync + 'static,
+        E: fmt::Display,
+    {
+        self.by_index.insert(column, converter(convert));
+    }
+
+    /// Sets the converter of the column with the given header name.
+    pub fn set_named<N, F, E>(&mut self, name: N, convert: F)
+    where
+        N: AsRef<[u8]>,
+        F: Fn(&[u8]) -> Result<Cow<'_, [u8]>, E> + Send + Sync + 'static,
+        E: fmt::Display,
+    {
+        self.by_name
+            .insert(name.as_ref().to_vec(), converter(convert));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_index.is_empty() && self.by_name.is_empty()
+    }
+
+    pub(crate) fn get(&self, column: usize, name: Option<&[u8]>) -> Option<&Converter> {
+        self.by_index
+            .get(&column)
+            .or_else(|| name.and_then(|name| self.by_name.get(name)))
+    }
+}
+
+impl PartialEq for Converters {
+    fn eq(&self, other: &Converters) -> bool {
+        fn same<K: Eq + core::hash::Hash>(
+            a: &HashMap<K, Converter>,
+            b: &HashMap<K, Converter>,
+        ) -> bool {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(key, f)| b.get(key).is_some_and(|g| Arc::ptr_eq(f, g)))
+        }
+        same(&self.by_index, &other.by_index) && same(&self.by_name, &other.by_name)
+    }
+}
+
+impl Eq for Converters {}
+
+impl fmt::Debug for Converters {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut indices: Vec<_> = self.by_index.keys().collect();
+        indices.sort();
+        let mut names: Vec<_> = self
+            .by_name
+            .keys()
+            .map(|name| String::from_utf8_lossy(name))
+            .collect();
+        names.sort();
+        f.debug_struct("Converters")
+            .field("columns", &indices)
+            .field("names", &names)
+            .finish()
+    }
+}
+
+pub(crate) fn map_columns(
+    arena: &ByteRecordArena,
+    converters: &Converters,
+) -> Result<ByteRecordArena, ConvertError> {
+    let headers = arena.headers();
+    let names: Vec<Option<&[u8]>> = match headers {
+        Some(headers) => headers.iter().map(Some).collect(),
+        None => Vec::new(),
+    };
+    let mut inner = RawRecordArena::new();
+    let mut field_data = Vec::new();
+    let mut field_ends = Vec::new();
+    for (row, record) in arena.iter().enumerate() {
+        field_data.clear();
+        field_ends.clear();
+        for (column, field) in record.iter().enumerate() {
+            let name = names.get(column).copied().flatten();
+            match converters.get(column, name) {
+                Some(convert) => {
+                    let converted = convert(field).map_err(|message| ConvertError {
+                        row,
+                        column,
+                        message,
+                    })?;
+                    field_data.extend_from_slice(&converted);
+                }
+                None => field_data.extend_from_slice(field),
+            }
+            field_ends.push(field_data.len());
+        }
+        inner.push_record(&field_data, &field_ends);
+    }
+    Ok(ByteRecordArena {
+        inner,
+        start_pos: arena.start_pos,
+        headers_inner: headers.cloned(),
+        trailer_inner: arena.trailer_inner.clone(),
+        bytes_init: 0,
+    })
+}
@@ -0,0 +1,317 @@
+//! Date and time fields with `chrono`.
+//!
+//! The `naive_date`, `naive_time` and `naive_date_time` modules are meant to be used with
+//! `#[serde(with = "sleek_csv::datetime::naive_date")]`. When deserializing from an arena,
+//! the fields are parsed with the formats set per column in `DateTimeFormats`, or with
+//! the default formats. Serializing writes the default formats.
+//!
+//! Columns can be extracted without Serde with `ByteRecordArena::parse_datetime_column`.
+
+#[cfg(feature = "serde")]
+use core::convert::TryFrom;
+use std::collections::HashMap;
+
+#[cfg(feature = "serde")]
+use chrono::{Datelike, Timelike};
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+
+use crate::column::{self, ColumnError, FromStrBytes, ParseError};
+use crate::ByteRecordArena;
+
+/// The kinds of date and time values.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DateTimeKind {
+    Date,
+    Time,
+    DateTime,
+}
+
+impl DateTimeKind {
+    /// The format used when no format is set for the column.
+    /// `%.f` accepts a missing fraction when parsing.
+    pub fn default_format(self) -> &'static str {
+        match self {
+            DateTimeKind::Date => "%Y-%m-%d",
+            DateTimeKind::Time => "%H:%M:%S%.f",
+            DateTimeKind::DateTime => "%Y-%m-%d %H:%M:%S%.f",
+        }
+    }
+
+    // The deserializer recognizes these newtype struct names, and passes the parsed value
+    // to the visitor as an `i128`, encoded with `DateTimeBits`.
+    #[cfg(feature = "serde")]
+    pub(crate) fn newtype_name(self) -> &'static str {
+        match self {
+            DateTimeKind::Date => "$sleek_csv::NaiveDate",
+            DateTimeKind::Time => "$sleek_csv::NaiveTime",
+            DateTimeKind::DateTime => "$sleek_csv::NaiveDateTime",
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    pub(crate) fn from_newtype_name(name: &str) -> Option<DateTimeKind> {
+        [
+            DateTimeKind::Date,
+            DateTimeKind::Time,
+            DateTimeKind::DateTime,
+        ]
+        .iter()
+        .copied()
+        .find(|kind| kind.newtype_name() == name)
+    }
+
+    /// Parses the field with the format into the `DateTimeBits` of the kind.
+    #[cfg(feature = "serde")]
+    pub(crate) fn parse_bits(self, field: &str, format: &str) -> Result<i128, chrono::ParseError> {
+        Ok(match self {
+            DateTimeKind::Date => NaiveDate::parse_from_str(field, format)?.to_bits(),
+            DateTimeKind::Time => NaiveTime::parse_from_str(field, format)?.to_bits(),
+            DateTimeKind::DateTime => NaiveDateTime::parse_from_str(field, format)?.to_bits(),
+        })
+    }
+}
+
+/// A lossless integer encoding of the parsed values, so that the deserializer can pass
+/// them to the `serde` modules without formatting them into strings to be parsed again.
+/// Dates are days from the Common Era, times are seconds from midnight in the high
+/// and nanoseconds in the low 32 bits, and date times have the date in the high 64 bits.
+#[cfg(feature = "serde")]
+pub(crate) trait DateTimeBits: Sized {
+    fn to_bits(&self) -> i128;
+    fn from_bits(bits: i128) -> Option<Self>;
+}
+
+#[cfg(feature = "serde")]
+impl DateTimeBits for NaiveDate {
+    fn to_bits(&self) -> i128 {
+        i128::from(self.num_days_from_ce())
+    }
+
+    fn from_bits(bits: i128) -> Option<NaiveDate> {
+        NaiveDate::from_num_days_from_ce_opt(i32::try_from(bits).ok()?)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl DateTimeBits for NaiveTime {
+    fn to_bits(&self) -> i128 {
+        // The nanoseconds exceed a second only on leap seconds
+        (i128::from(self.num_seconds_from_midnight()) << 32) | i128::from(self.nanosecond())
+    }
+
+    fn from_bits(bits: i128) -> Option<NaiveTime> {
+        let seconds = u32::try_from(bits >> 32).ok()?;
+        NaiveTime::from_num_seconds_from_midnight_opt(seconds, bits as u32)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl DateTimeBits for NaiveDateTime {
+    fn to_bits(&self) -> i128 {
+        (self.date().to_bits() << 64) | self.time().to_bits()
+    }
+
+    fn from_bits(bits: i128) -> Option<NaiveDateTime> {
+        let date = NaiveDate::from_bits(bits >> 64)?;
+        let time = NaiveTime::from_bits(bits & i128::from(u64::MAX))?;
+        Some(date.and_time(time))
+    }
+}
+
+/// Date and time formats per column, in the `strftime` syntax of `chrono`.
+/// E.g. `%Y/%m/%d %H:%M:%S` for `2019/10/01 1:11:11`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DateTimeFormats {
+    columns: HashMap<usize, String>,
+}
+
+impl DateTimeFormats {
+    pub fn new() -> DateTimeFormats {
+        DateTimeFormats::default()
+    }
+
+    /// Sets the format of the 0-based column.
+    pub fn set_column(&mut self, column: usize, format: &str) {
+        self.columns.insert(column, format.to_owned());
+    }
+
+    /// Returns the format of the column, or the default format of the kind.
+    pub fn format(&self, column: usize, kind: DateTimeKind) -> &str {
+        self.columns
+            .get(&column)
+            .map_or(kind.default_format(), |format| format.as_str())
+    }
+}
+
+/// Date and time types that can be parsed from fields with a format.
+pub trait DateTimeField: Sized {
+    fn parse_field(field: &[u8], format: &str) -> Result<Self, ParseError>;
+}
+
+macro_rules! impl_datetime_field {
+    ($($type:ty => $kind:expr),*) => {$(
+        impl DateTimeField for $type {
+            fn parse_field(field: &[u8], format: &str) -> Result<$type, ParseError> {
+                if field.is_empty() {
+                    return Err(ParseError::Empty);
+                }
+                let s = std::str::from_utf8(field).map_err(|_| ParseError::InvalidUtf8)?;
+                <$type>::parse_from_str(s, format).map_err(|_| ParseError::InvalidDateTime)
+            }
+        }
+
+        impl FromStrBytes for $type {
+            fn from_str_bytes(bytes: &[u8]) -> Result<$type, ParseError> {
+                Self::parse_field(bytes, $kind.default_format())
+            }
+        }
+    )*}
+}
+
+impl_datetime_field!(
+    NaiveDate => DateTimeKind::Date,
+    NaiveTime => DateTimeKind::Time,
+    NaiveDateTime => DateTimeKind::DateTime
+);
+
+impl<T: DateTimeField> DateTimeField for Option<T> {
+    fn parse_field(field: &[u8], format: &str) -> Result<Option<T>, ParseError> {
+        if field.is_empty() {
+            Ok(None)
+        } else {
+            T::parse_field(field, format).map(Some)
+        }
+    }
+}
+
+pub(crate) fn parse_column<T: DateTimeField>(
+    arena: &ByteRecordArena,
+    column: usize,
+    format: &str,
+) -> Result<Vec<T>, ColumnError> {
+    column::parse_column_by(arena, column, |field| T::parse_field(field, format))
+}
+
+#[cfg(feature = "serde")]
+macro_rules! serde_module {
+    ($module:ident, $type:ty, $kind:expr, $expecting:expr) => {
+        #[doc = concat!("Serde support for `", stringify!($type), "`, for use with `#[serde(with = ...)]`.")]
+        pub mod $module {
+            use core::fmt;
+
+            use serde::de::{self, Deserializer, Visitor};
+            use serde::ser::Serializer;
+
+            use super::*;
+
+            struct DateTimeVisitor;
+
+            impl<'de> Visitor<'de> for DateTimeVisitor {
+                type Value = $type;
+
+                fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    f.write_str($expecting)
+                }
+
+                fn visit_str<E: de::Error>(self, s: &str) -> Result<$type, E> {
+                    <$type>::parse_from_str(s, $kind.default_format()).map_err(E::custom)
+                }
+
+                // From the deserializer of this crate, already parsed
+                fn visit_i128<E: de::Error>(self, bits: i128) -> Result<$type, E> {
+                    <$type>::from_bits(bits).ok_or_else(|| E::custom("invalid date or time"))
+                }
+
+                fn visit_newtype_struct<D: Deserializer<'de>>(
+                    self,
+                    deserializer: D,
+                ) -> Result<$type, D::Error> {
+                    deserializer.deserialize_str(self)
+                }
+            }
+
+            pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<$type, D::Error> {
+                deserializer.deserialize_newtype_struct($kind.newtype_name(), DateTimeVisitor)
+            }
+
+            pub fn serialize<S: Serializer>(value: &$type, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(&value.format($kind.default_format()))
+            }
+
+            /// The same for `Option`s, for nullable columns.
+            pub mod option {
+                use serde::{Deserialize, Deserializer, Serializer};
+
+                use super::*;
+
+                #[derive(Deserialize)]
+                struct Wrap(#[serde(with = "super")] $type);
+
+                pub fn deserialize<'de, D: Deserializer<'de>>(
+                    deserializer: D,
+                ) -> Result<Option<$type>, D::Error> {
+                    Ok(Option::<Wrap>::deserialize(deserializer)?.map(|Wrap(value)| value))
+                }
+
+                pub fn serialize<S: Serializer>(
+                    value: &Option<$type>,
+                    serializer: S,
+                ) -> Result<S::Ok, S::Error> {
+                    match value {
+                        Some(value) => super::serialize(value, serializer),
+                        None => serializer.serialize_none(),
+                    }
+                }
+            }
+        }
+    };
+}
+
+#[cfg(feature = "serde")]
+serde_module!(naive_date, NaiveDate, DateTimeKind::Date, "a date");
+#[cfg(feature = "serde")]
+serde_module!(naive_time, NaiveTime, DateTimeKind::Time, "a time");
+#[cfg(feature = "serde")]
+serde_module!(
+    naive_date_time,
+    NaiveDateTime,
+    DateTimeKind::DateTime,
+    "a date and time"
+);
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_datetime_bits() {
+    let date_time = NaiveDate::from_ymd_opt(-44, 3, 15)
+        .unwrap()
+        .and_hms_nano_opt(23, 59, 59, 1_500_000_000)
+        .unwrap();
+    assert_eq!(
+        NaiveDateTime::from_bits(date_time.to_bits()),
+        Some(date_time)
+    );
+    assert_eq!(
+        NaiveTime::from_bits(date_time.time().to_bits()),
+        Some(date_time.time())
+    );
+    assert_eq!(NaiveTime::from_bits(-1), None);
+}
+
+#[test]
+fn test_default_formats() {
+    assert_eq!(
+        NaiveDateTime::from_str_bytes(b"2019-10-01 01:11:11.5"),
+        Ok(NaiveDate::from_ymd_opt(2019, 10, 1)
+            .unwrap()
+            .and_hms_milli_opt(1, 11, 11, 500)
+            .unwrap())
+    );
+    assert_eq!(
+        NaiveTime::from_str_bytes(b"1:11:11"),
+        Ok(NaiveTime::from_hms_opt(1, 11, 11).unwrap())
+    );
+    assert_eq!(
+        NaiveDate::from_str_bytes(b"2019/10/01"),
+        Err(ParseError::InvalidDateTime)
+    );
+}
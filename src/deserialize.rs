@@ -1,5 +1,7 @@
+use alloc::borrow::Cow;
 use core::fmt;
 use core::iter;
+use core::marker::PhantomData;
 use core::num;
 use core::str;
 use std::error::Error as StdError;
@@ -7,12 +9,17 @@ use std::error::Error as StdError;
 use serde::de::value::BorrowedBytesDeserializer;
 use serde::de::{
     Deserialize, DeserializeSeed, Deserializer, EnumAccess, Error as SerdeError, IntoDeserializer,
-    MapAccess, SeqAccess, Unexpected, VariantAccess, Visitor,
+    MapAccess, SeqAccess, VariantAccess, Visitor,
 };
 use serde::serde_if_integer128;
 
+#[cfg(feature = "chrono")]
+use crate::datetime::{DateTimeFormats, DateTimeKind};
 use crate::error::{Error, ErrorKind};
 use crate::raw::{RawRecord, RawRecordArena, RawRecordIter};
+use crate::reader::{trim_ascii, Trim};
+use crate::value::{self, Value};
+use crate::{BoolFormat, BoolFormats, Converters, NullTokens, NumberFormat};
 
 use self::DeserializeErrorKind as DEK;
 
@@ -52,36 +59,124 @@ pub fn deserialize_byte_record<'de, D: Deserialize<'de>>(
 }
 */
 
+/// Options for `ByteRecordArena::deserialize_with`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DeserializeOptions {
+    /// Deserializes structs and maps by matching the header names to the field names.
+    /// By default the fields are deserialized by position, and the headers are only used
+    /// to look up the per-column formats and converters.
+    pub match_headers: bool,
+    /// Trims the headers and/or fields before deserializing them. Unlike `Reader::set_trim`,
+    /// this doesn't change the data in the arena.
+    pub trim: Trim,
+    /// Fields matching the null tokens deserialize into `None` when the type is an `Option`.
+    pub null_tokens: NullTokens,
+    /// The format of the integer and float fields.
+    pub number_format: NumberFormat,
+    /// The formats of the boolean fields, also used when inferring the type of a field.
+    /// The formats of specific columns are only used if there are headers.
+    pub bool_formats: BoolFormats,
+    /// The formats of the date and time fields deserialized with the `datetime` modules.
+    #[cfg(feature = "chrono")]
+    pub datetime_formats: DateTimeFormats,
+    /// Maps the columns whose headers share a prefix followed by the separator, e.g.
+    /// `billing.city` and `billing.zip` with `.`, to a nested struct or map named after the
+    /// prefix. The columns of a group must be adjacent. Groups can be nested.
+    pub nested_separator: Option<Vec<u8>>,
+    /// Passes the fields to Serde as strings when the type isn't known, e.g. when the columns
+    /// are buffered for a `#[serde(flatten)]` field. By default the type is inferred,
+    /// so that the fields can be deserialized into numbers later, and empty fields and
+    /// null tokens are passed as `None`, but then numeric and empty fields can't be
+    /// collected into a `HashMap<String, String>`.
+    pub buffer_as_strings: bool,
+    /// Converters applied to the fields deserialized as strings.
+    /// Converted fields can't be deserialized as borrowed `&str`.
+    pub converters: Converters,
+}
+
 pub fn deserialize_byte_record_arena<'de, D: Deserialize<'de>>(
     records: &'de RawRecordArena,
-    headers: Option<&'de RawRecord>,
+    headers: Option<RawRecord<'de>>,
+    options: &DeserializeOptions,
     output: &mut Vec<D>,
 ) -> Result<usize, Error> {
     let before = output.len();
     for (i, record) in records.iter().enumerate() {
-        let mut deser = DeRecordWrap(DeByteRecord {
-            it: record.iter().peekable(),
-            headers: None, // headers.map(|r| r.iter()),
-            field: 0,
-        });
-        output.push(D::deserialize(&mut deser).map_err(|err| {
-            Error::new(ErrorKind::Deserialize {
-                index: i,
-                err,
-                field_data: record.field_data.to_owned(),
-                field_ends: record.field_ends.to_owned(),
-            })
-        })?);
+        output.push(deserialize_raw_record(record, i, headers, options)?);
+    }
+    Ok(output.len() - before)
+}
+
+/// Like `deserialize_byte_record_arena`, but each record is deserialized with
+/// the seed `&mut *context`.
+pub fn deserialize_byte_record_arena_seed<'de, C, V>(
+    records: &'de RawRecordArena,
+    headers: Option<RawRecord<'de>>,
+    options: &DeserializeOptions,
+    context: &mut C,
+    output: &mut Vec<V>,
+) -> Result<usize, Error>
+where
+    for<'c> &'c mut C: DeserializeSeed<'de, Value = V>,
+{
+    let before = output.len();
+    for (i, record) in records.iter().enumerate() {
+        output.push(deserialize_raw_record_seed(
+            record,
+            i,
+            headers,
+            options,
+            &mut *context,
+        )?);
     }
     Ok(output.len() - before)
 }
 
+/// Deserializes a single record. `index` is the index reported in the error.
+pub fn deserialize_raw_record<'de, D: Deserialize<'de>>(
+    record: RawRecord<'de>,
+    index: usize,
+    headers: Option<RawRecord<'de>>,
+    options: &DeserializeOptions,
+) -> Result<D, Error> {
+    deserialize_raw_record_seed(record, index, headers, options, PhantomData)
+}
+
+fn deserialize_raw_record_seed<'de, S: DeserializeSeed<'de>>(
+    record: RawRecord<'de>,
+    index: usize,
+    headers: Option<RawRecord<'de>>,
+    options: &DeserializeOptions,
+    seed: S,
+) -> Result<S::Value, Error> {
+    let mut deser = DeRecordWrap(DeByteRecord {
+        it: record.iter().peekable(),
+        headers: headers
+            .filter(|_| options.match_headers)
+            .map(|r| r.iter().peekable()),
+        names: headers,
+        field: 0,
+        options,
+    });
+    seed.deserialize(DeRecordRoot(&mut deser)).map_err(|err| {
+        Error::new(ErrorKind::Deserialize {
+            index,
+            err,
+            field_data: record.field_data.to_owned(),
+            field_ends: record.field_ends.to_owned(),
+        })
+    })
+}
+
 #[test]
 fn test_deser() {
+    use crate::{ByteRecordArena, Reader};
+
     let record = r#""BSCD","RSCD","CCCD","NOLN","CBCD","STHB","BSNJ","BSNK","STYB","CRE_YMD","CRE_PGM_ID"
 "01000002","R2000119","0040001","&1","04101","20170923","台湾・古龍華","ｺﾘﾕｳｶ","19981221",2019/10/09 8:10:09,"C006"
 "#;
 
+    #[allow(dead_code)]
     #[derive(serde::Deserialize, Debug, Clone)]
     struct Record<'i> {
         bscd: Option<&'i str>,
@@ -99,7 +194,7 @@ fn test_deser() {
     let mut arena = ByteRecordArena::new();
     let mut reader = Reader::new(true, b',');
     let mut records = Vec::<Record>::new();
-    reader.fill_arena(record.as_bytes(), &mut arena);
+    reader.fill_arena(record.as_bytes(), &mut arena).unwrap();
     arena.deserialize(&mut records).unwrap();
     println!("{:?}", arena);
     println!("{:?}", records);
@@ -126,20 +221,44 @@ trait DeRecord<'r> {
     fn has_headers(&self) -> bool;
 
     /// Extracts the next string header value from the underlying record.
+    #[allow(dead_code)]
     fn next_header(&mut self) -> Result<Option<&'r str>, DeserializeError>;
 
     /// Extracts the next raw byte header value from the underlying record.
     fn next_header_bytes(&mut self) -> Result<Option<&'r [u8]>, DeserializeError>;
 
+    /// Peeks at the next raw byte header value from the underlying record.
+    fn peek_header_bytes(&mut self) -> Option<&'r [u8]>;
+
+    /// Returns the separator of the prefixes of nested column groups.
+    fn nested_separator(&self) -> Option<&[u8]>;
+
     /// Extracts the next string field from the underlying record.
     fn next_field(&mut self) -> Result<&'r str, DeserializeError>;
 
     /// Extracts the next raw byte field from the underlying record.
     fn next_field_bytes(&mut self) -> Result<&'r [u8], DeserializeError>;
 
+    /// Extracts the next numeric field, converted from the number format into the Rust syntax.
+    fn next_number_field(&mut self) -> Result<Cow<'r, str>, DeserializeError>;
+
+    /// Extracts the next string field, converted with the converter of its column.
+    fn next_converted_field(&mut self) -> Result<Cow<'r, str>, DeserializeError>;
+
+    /// Extracts the next boolean field, parsed with the boolean format of its column.
+    fn next_bool_field(&mut self) -> Result<bool, DeserializeError>;
+
+    /// Extracts the next date or time field, parsed with the format of the column
+    /// and encoded as `DateTimeBits`.
+    #[cfg(feature = "chrono")]
+    fn next_datetime_field(&mut self, kind: DateTimeKind) -> Result<i128, DeserializeError>;
+
     /// Peeks at the next field from the underlying record.
     fn peek_field(&mut self) -> Option<&'r [u8]>;
 
+    /// Tells if the field stands for a missing value.
+    fn is_null(&self, field: &[u8]) -> bool;
+
     /// Returns an error corresponding to the most recently extracted field.
     fn error(&self, kind: DeserializeErrorKind) -> DeserializeError;
 
@@ -168,6 +287,16 @@ impl<'r, T: DeRecord<'r>> DeRecord<'r> for DeRecordWrap<T> {
         self.0.next_header_bytes()
     }
 
+    #[inline]
+    fn peek_header_bytes(&mut self) -> Option<&'r [u8]> {
+        self.0.peek_header_bytes()
+    }
+
+    #[inline]
+    fn nested_separator(&self) -> Option<&[u8]> {
+        self.0.nested_separator()
+    }
+
     #[inline]
     fn next_field(&mut self) -> Result<&'r str, DeserializeError> {
         self.0.next_field()
@@ -178,11 +307,37 @@ impl<'r, T: DeRecord<'r>> DeRecord<'r> for DeRecordWrap<T> {
         self.0.next_field_bytes()
     }
 
+    #[inline]
+    fn next_number_field(&mut self) -> Result<Cow<'r, str>, DeserializeError> {
+        self.0.next_number_field()
+    }
+
+    #[inline]
+    fn next_converted_field(&mut self) -> Result<Cow<'r, str>, DeserializeError> {
+        self.0.next_converted_field()
+    }
+
+    #[inline]
+    fn next_bool_field(&mut self) -> Result<bool, DeserializeError> {
+        self.0.next_bool_field()
+    }
+
+    #[cfg(feature = "chrono")]
+    #[inline]
+    fn next_datetime_field(&mut self, kind: DateTimeKind) -> Result<i128, DeserializeError> {
+        self.0.next_datetime_field(kind)
+    }
+
     #[inline]
     fn peek_field(&mut self) -> Option<&'r [u8]> {
         self.0.peek_field()
     }
 
+    #[inline]
+    fn is_null(&self, field: &[u8]) -> bool {
+        self.0.is_null(field)
+    }
+
     #[inline]
     fn error(&self, kind: DeserializeErrorKind) -> DeserializeError {
         self.0.error(kind)
@@ -281,13 +436,26 @@ impl<'r> DeRecord<'r> for DeStringRecord<'r> {
     }
 }
 */
-struct DeByteRecord<'r> {
+struct DeByteRecord<'r, 'o> {
     it: iter::Peekable<RawRecordIter<'r>>,
-    headers: Option<RawRecordIter<'r>>,
+    headers: Option<iter::Peekable<RawRecordIter<'r>>>,
+    names: Option<RawRecord<'r>>,
     field: u64,
+    options: &'o DeserializeOptions,
+}
+
+impl<'r, 'o> DeByteRecord<'r, 'o> {
+    fn name(&self, column: u64) -> Option<&'r [u8]> {
+        self.names.and_then(|names| names.get(column as usize))
+    }
+
+    /// Returns the boolean format of the 0-based column.
+    fn bool_format(&self, column: u64) -> &'o BoolFormat {
+        self.options.bool_formats.format(self.name(column))
+    }
 }
 
-impl<'r> DeRecord<'r> for DeByteRecord<'r> {
+impl<'r, 'o> DeRecord<'r> for DeByteRecord<'r, 'o> {
     #[inline]
     fn has_headers(&self) -> bool {
         self.headers.is_some()
@@ -306,7 +474,26 @@ impl<'r> DeRecord<'r> for DeByteRecord<'r> {
 
     #[inline]
     fn next_header_bytes(&mut self) -> Result<Option<&'r [u8]>, DeserializeError> {
-        Ok(self.headers.as_mut().and_then(|it| it.next()))
+        let trim = self.options.trim.headers();
+        Ok(self
+            .headers
+            .as_mut()
+            .and_then(|it| it.next())
+            .map(|header| if trim { trim_ascii(header) } else { header }))
+    }
+
+    #[inline]
+    fn peek_header_bytes(&mut self) -> Option<&'r [u8]> {
+        let trim = self.options.trim.headers();
+        self.headers
+            .as_mut()
+            .and_then(|it| it.peek())
+            .map(|&header| if trim { trim_ascii(header) } else { header })
+    }
+
+    #[inline]
+    fn nested_separator(&self) -> Option<&[u8]> {
+        self.options.nested_separator.as_deref()
     }
 
     #[inline]
@@ -321,7 +508,11 @@ impl<'r> DeRecord<'r> for DeByteRecord<'r> {
         match self.it.next() {
             Some(field) => {
                 self.field += 1;
-                Ok(field)
+                Ok(if self.options.trim.fields() {
+                    trim_ascii(field)
+                } else {
+                    field
+                })
             }
             None => Err(DeserializeError {
                 field: None,
@@ -332,13 +523,77 @@ impl<'r> DeRecord<'r> for DeByteRecord<'r> {
 
     #[inline]
     fn peek_field(&mut self) -> Option<&'r [u8]> {
-        self.it.peek().map(|s| *s)
+        let trim = self.options.trim.fields();
+        self.it
+            .peek()
+            .map(|&field| if trim { trim_ascii(field) } else { field })
+    }
+
+    #[inline]
+    fn is_null(&self, field: &[u8]) -> bool {
+        self.options.null_tokens.is_null(field)
+    }
+
+    #[cfg(feature = "chrono")]
+    fn next_datetime_field(&mut self, kind: DateTimeKind) -> Result<i128, DeserializeError> {
+        let field = self.next_field()?;
+        let column = self.field - 1;
+        let format = self.options.datetime_formats.format(column as usize, kind);
+        kind.parse_bits(field, format).map_err(|err| {
+            self.error(DEK::ParseDateTime {
+                field: column,
+                err: err.to_string(),
+            })
+        })
+    }
+
+    fn next_number_field(&mut self) -> Result<Cow<'r, str>, DeserializeError> {
+        let field = self.next_field_bytes()?;
+        match self.options.number_format.normalize(field) {
+            Cow::Borrowed(field) => str::from_utf8(field).map(Cow::Borrowed),
+            Cow::Owned(field) => String::from_utf8(field)
+                .map(Cow::Owned)
+                .map_err(|err| err.utf8_error()),
+        }
+        .map_err(|err| self.error(DEK::InvalidUtf8(err)))
+    }
+
+    fn next_converted_field(&mut self) -> Result<Cow<'r, str>, DeserializeError> {
+        let field = self.next_field_bytes()?;
+        let column = self.field - 1;
+        let convert = match self
+            .options
+            .converters
+            .get(column as usize, self.name(column))
+        {
+            Some(convert) => convert,
+            None => {
+                return str::from_utf8(field)
+                    .map(Cow::Borrowed)
+                    .map_err(|err| self.error(DEK::InvalidUtf8(err)))
+            }
+        };
+        match convert(field) {
+            Ok(Cow::Borrowed(field)) => str::from_utf8(field).map(Cow::Borrowed),
+            Ok(Cow::Owned(field)) => String::from_utf8(field)
+                .map(Cow::Owned)
+                .map_err(|err| err.utf8_error()),
+            Err(err) => return Err(self.error(DEK::Convert { field: column, err })),
+        }
+        .map_err(|err| self.error(DEK::InvalidUtf8(err)))
+    }
+
+    fn next_bool_field(&mut self) -> Result<bool, DeserializeError> {
+        let field = self.next_field_bytes()?;
+        self.bool_format(self.field - 1)
+            .parse(field)
+            .ok_or_else(|| self.error(DEK::ParseBool(String::from_utf8_lossy(field).into_owned())))
     }
 
     fn error(&self, kind: DeserializeErrorKind) -> DeserializeError {
         DeserializeError {
             field: Some(self.field.saturating_sub(1)),
-            kind: kind,
+            kind,
         }
     }
 
@@ -346,49 +601,52 @@ impl<'r> DeRecord<'r> for DeByteRecord<'r> {
         &mut self,
         visitor: V,
     ) -> Result<V::Value, DeserializeError> {
-        let x = self.next_field_bytes()?;
-        if x == b"true" {
-            return visitor.visit_bool(true);
-        } else if x == b"false" {
-            return visitor.visit_bool(false);
-        } else if let Some(n) = try_positive_integer64_bytes(x) {
-            return visitor.visit_u64(n);
-        } else if let Some(n) = try_negative_integer64_bytes(x) {
-            return visitor.visit_i64(n);
+        let field = self.next_field_bytes()?;
+        if self.options.buffer_as_strings {
+            return match str::from_utf8(field) {
+                Ok(s) => visitor.visit_str(s),
+                Err(_) => visitor.visit_bytes(field),
+            };
         }
-        serde_if_integer128! {
-            if let Some(n) = try_positive_integer128_bytes(x) {
-                return visitor.visit_u128(n);
-            } else if let Some(n) = try_negative_integer128_bytes(x) {
-                return visitor.visit_i128(n);
-            }
+        if self.is_null(field) {
+            return visitor.visit_none();
         }
-        if let Some(n) = try_float_bytes(x) {
-            visitor.visit_f64(n)
-        } else if let Ok(s) = str::from_utf8(x) {
-            visitor.visit_str(s)
-        } else {
-            visitor.visit_bytes(x)
+        let normalized = self.options.number_format.normalize(field);
+        let x = &*normalized;
+        match value::infer(field, x, self.bool_format(self.field - 1)) {
+            Value::Bool(b) => visitor.visit_bool(b),
+            Value::UInt(n) => visitor.visit_u64(n),
+            Value::Int(n) => visitor.visit_i64(n),
+            Value::Float(n) => visitor.visit_f64(n),
+            Value::Null => visitor.visit_none(),
+            Value::Str(s) => {
+                // Integers too large for 64 bits are inferred as strings
+                serde_if_integer128! {
+                    if let Some(n) = try_positive_integer128_bytes(x) {
+                        return visitor.visit_u128(n);
+                    } else if let Some(n) = try_negative_integer128_bytes(x) {
+                        return visitor.visit_i128(n);
+                    }
+                }
+                visitor.visit_str(s)
+            }
+            Value::Bytes(b) => visitor.visit_bytes(b),
         }
     }
 }
 
 macro_rules! deserialize_int {
     ($method:ident, $visit:ident, $inttype:ty) => {
-        fn $method<V: Visitor<'de>>(
-            self,
-            visitor: V,
-        ) -> Result<V::Value, Self::Error> {
-            let field = self.next_field()?;
-            let num =
-                if field.starts_with("0x") {
-                    <$inttype>::from_str_radix(&field[2..], 16)
-                } else {
-                    field.parse()
-                };
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+            let field = self.next_number_field()?;
+            let num = if let Some(hex) = field.strip_prefix("0x") {
+                <$inttype>::from_str_radix(hex, 16)
+            } else {
+                field.parse()
+            };
             visitor.$visit(num.map_err(|err| self.error(DEK::ParseInt(err)))?)
         }
-    }
+    };
 }
 
 impl<'a, 'de: 'a, T: DeRecord<'de>> Deserializer<'de> for &'a mut DeRecordWrap<T> {
@@ -399,11 +657,7 @@ impl<'a, 'de: 'a, T: DeRecord<'de>> Deserializer<'de> for &'a mut DeRecordWrap<T
     }
 
     fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
-        visitor.visit_bool(
-            self.next_field()?
-                .parse()
-                .map_err(|err| self.error(DEK::ParseBool(err)))?,
-        )
+        visitor.visit_bool(self.next_bool_field()?)
     }
 
     deserialize_int!(deserialize_u8, visit_u8, u8);
@@ -423,7 +677,7 @@ impl<'a, 'de: 'a, T: DeRecord<'de>> Deserializer<'de> for &'a mut DeRecordWrap<T
 
     fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
         visitor.visit_f32(
-            self.next_field()?
+            self.next_number_field()?
                 .parse()
                 .map_err(|err| self.error(DEK::ParseFloat(err)))?,
         )
@@ -431,7 +685,7 @@ impl<'a, 'de: 'a, T: DeRecord<'de>> Deserializer<'de> for &'a mut DeRecordWrap<T
 
     fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
         visitor.visit_f64(
-            self.next_field()?
+            self.next_number_field()?
                 .parse()
                 .map_err(|err| self.error(DEK::ParseFloat(err)))?,
         )
@@ -450,12 +704,17 @@ impl<'a, 'de: 'a, T: DeRecord<'de>> Deserializer<'de> for &'a mut DeRecordWrap<T
     }
 
     fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
-        self.next_field()
-            .and_then(|f| visitor.visit_borrowed_str(f))
+        match self.next_converted_field()? {
+            Cow::Borrowed(field) => visitor.visit_borrowed_str(field),
+            Cow::Owned(field) => visitor.visit_string(field),
+        }
     }
 
     fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
-        self.next_field().and_then(|f| visitor.visit_str(f.into()))
+        match self.next_converted_field()? {
+            Cow::Borrowed(field) => visitor.visit_str(field),
+            Cow::Owned(field) => visitor.visit_string(field),
+        }
     }
 
     fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
@@ -471,8 +730,8 @@ impl<'a, 'de: 'a, T: DeRecord<'de>> Deserializer<'de> for &'a mut DeRecordWrap<T
     fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
         match self.peek_field() {
             None => visitor.visit_none(),
-            Some(f) if f.is_empty() => {
-                self.next_field().expect("empty field");
+            Some(field) if self.is_null(field) => {
+                self.next_field_bytes().expect("null field");
                 visitor.visit_none()
             }
             Some(_) => visitor.visit_some(self),
@@ -496,6 +755,12 @@ impl<'a, 'de: 'a, T: DeRecord<'de>> Deserializer<'de> for &'a mut DeRecordWrap<T
         _name: &'static str,
         visitor: V,
     ) -> Result<V::Value, Self::Error> {
+        #[cfg(feature = "chrono")]
+        {
+            if let Some(kind) = DateTimeKind::from_newtype_name(_name) {
+                return visitor.visit_i128(self.next_datetime_field(kind)?);
+            }
+        }
         visitor.visit_newtype_struct(self)
     }
 
@@ -541,8 +806,9 @@ impl<'a, 'de: 'a, T: DeRecord<'de>> Deserializer<'de> for &'a mut DeRecordWrap<T
         }
     }
 
-    fn deserialize_identifier<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
-        Err(self.error(DEK::Unsupported("deserialize_identifier".into())))
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        // E.g. the tag field of an internally tagged enum
+        self.deserialize_str(visitor)
     }
 
     fn deserialize_enum<V: Visitor<'de>>(
@@ -563,6 +829,86 @@ impl<'a, 'de: 'a, T: DeRecord<'de>> Deserializer<'de> for &'a mut DeRecordWrap<T
     }
 }
 
+/// The deserializer of a whole record.
+///
+/// It differs from the field deserializer in two ways: a record deserialized with
+/// `deserialize_any`, e.g. an internally tagged enum, is a map of the columns if there are
+/// headers and a sequence of the fields otherwise. And an enum takes its variant name from
+/// the first field, so its payload is matched with the headers from the second column on.
+struct DeRecordRoot<'a, T>(&'a mut DeRecordWrap<T>);
+
+macro_rules! forward_to_record {
+    ($($method:ident($($arg:ident: $type:ty),*);)*) => {$(
+        fn $method<V: Visitor<'de>>(
+            self,
+            $($arg: $type,)*
+            visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            self.0.$method($($arg,)* visitor)
+        }
+    )*};
+}
+
+impl<'a, 'de: 'a, T: DeRecord<'de>> Deserializer<'de> for DeRecordRoot<'a, T> {
+    type Error = DeserializeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if !self.0.has_headers() {
+            visitor.visit_seq(self.0)
+        } else {
+            visitor.visit_map(self.0)
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        // Skip the header of the variant name column.
+        self.0.next_header_bytes()?;
+        self.0.deserialize_enum(name, variants, visitor)
+    }
+
+    forward_to_record! {
+        deserialize_bool();
+        deserialize_u8();
+        deserialize_u16();
+        deserialize_u32();
+        deserialize_u64();
+        deserialize_i8();
+        deserialize_i16();
+        deserialize_i32();
+        deserialize_i64();
+        deserialize_f32();
+        deserialize_f64();
+        deserialize_char();
+        deserialize_str();
+        deserialize_string();
+        deserialize_bytes();
+        deserialize_byte_buf();
+        deserialize_option();
+        deserialize_unit();
+        deserialize_unit_struct(name: &'static str);
+        deserialize_newtype_struct(name: &'static str);
+        deserialize_seq();
+        deserialize_tuple(len: usize);
+        deserialize_tuple_struct(name: &'static str, len: usize);
+        deserialize_map();
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]);
+        deserialize_identifier();
+        deserialize_ignored_any();
+    }
+
+    serde_if_integer128! {
+        forward_to_record! {
+            deserialize_u128();
+            deserialize_i128();
+        }
+    }
+}
+
 impl<'a, 'de: 'a, T: DeRecord<'de>> EnumAccess<'de> for &'a mut DeRecordWrap<T> {
     type Error = DeserializeError;
     type Variant = Self;
@@ -584,30 +930,33 @@ impl<'a, 'de: 'a, T: DeRecord<'de>> VariantAccess<'de> for &'a mut DeRecordWrap<
         Ok(())
     }
 
+    // The payload of the variant follows the field holding the variant name.
+
     fn newtype_variant_seed<U: DeserializeSeed<'de>>(
         self,
-        _seed: U,
+        seed: U,
     ) -> Result<U::Value, Self::Error> {
-        let unexp = Unexpected::UnitVariant;
-        Err(DeserializeError::invalid_type(unexp, &"newtype variant"))
+        seed.deserialize(self)
     }
 
     fn tuple_variant<V: Visitor<'de>>(
         self,
         _len: usize,
-        _visitor: V,
+        visitor: V,
     ) -> Result<V::Value, Self::Error> {
-        let unexp = Unexpected::UnitVariant;
-        Err(DeserializeError::invalid_type(unexp, &"tuple variant"))
+        visitor.visit_seq(self)
     }
 
     fn struct_variant<V: Visitor<'de>>(
         self,
         _fields: &'static [&'static str],
-        _visitor: V,
+        visitor: V,
     ) -> Result<V::Value, Self::Error> {
-        let unexp = Unexpected::UnitVariant;
-        Err(DeserializeError::invalid_type(unexp, &"struct variant"))
+        if !self.has_headers() {
+            visitor.visit_seq(self)
+        } else {
+            visitor.visit_map(self)
+        }
     }
 }
 
@@ -634,19 +983,116 @@ impl<'a, 'de: 'a, T: DeRecord<'de>> MapAccess<'de> for &'a mut DeRecordWrap<T> {
         seed: K,
     ) -> Result<Option<K::Value>, Self::Error> {
         assert!(self.has_headers());
-        let field = match self.next_header_bytes()? {
+        self.next_column_key(b"", seed)
+    }
+
+    fn next_value_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<K::Value, Self::Error> {
+        self.next_column_value(b"", seed)
+    }
+}
+
+/// The next column of a map, as seen from a group of columns.
+enum Column<'r> {
+    /// A field and its name without the prefix of the group.
+    Field(&'r [u8]),
+    /// A nested group, the name of the group and the full prefix of its columns.
+    Group(&'r [u8], &'r [u8]),
+}
+
+// The header of a column is consumed along with its value, not its key,
+// as the columns of a nested group are consumed by the group.
+impl<'de, T: DeRecord<'de>> DeRecordWrap<T> {
+    /// Peeks at the next column if its header starts with the prefix.
+    fn peek_column(&mut self, prefix: &[u8]) -> Option<Column<'de>> {
+        let header = self.peek_header_bytes()?;
+        let name = header.strip_prefix(prefix)?;
+        if let Some(sep) = self.nested_separator().filter(|sep| !sep.is_empty()) {
+            if let Some(pos) = name.windows(sep.len()).position(|w| w == sep) {
+                let group_prefix = &header[..prefix.len() + pos + sep.len()];
+                return Some(Column::Group(&name[..pos], group_prefix));
+            }
+        }
+        Some(Column::Field(name))
+    }
+
+    fn next_column_key<K: DeserializeSeed<'de>>(
+        &mut self,
+        prefix: &[u8],
+        seed: K,
+    ) -> Result<Option<K::Value>, DeserializeError> {
+        let name = match self.peek_column(prefix) {
             None => return Ok(None),
-            Some(field) => field,
+            Some(Column::Field(name)) | Some(Column::Group(name, _)) => name,
         };
-        seed.deserialize(BorrowedBytesDeserializer::new(field))
+        seed.deserialize(BorrowedBytesDeserializer::new(name))
             .map(Some)
     }
 
+    fn next_column_value<K: DeserializeSeed<'de>>(
+        &mut self,
+        prefix: &[u8],
+        seed: K,
+    ) -> Result<K::Value, DeserializeError> {
+        match self.peek_column(prefix) {
+            Some(Column::Group(_, prefix)) => seed.deserialize(DeColumnGroup { de: self, prefix }),
+            _ => {
+                self.next_header_bytes()?;
+                seed.deserialize(self)
+            }
+        }
+    }
+}
+
+/// The deserializer of a group of columns with a common prefix, as a struct or a map.
+struct DeColumnGroup<'a, 'r, T> {
+    de: &'a mut DeRecordWrap<T>,
+    prefix: &'r [u8],
+}
+
+impl<'a, 'de: 'a, T: DeRecord<'de>> Deserializer<'de> for DeColumnGroup<'a, 'de, T> {
+    type Error = DeserializeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_map(self)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+impl<'a, 'de: 'a, T: DeRecord<'de>> MapAccess<'de> for DeColumnGroup<'a, 'de, T> {
+    type Error = DeserializeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        self.de.next_column_key(self.prefix, seed)
+    }
+
     fn next_value_seed<K: DeserializeSeed<'de>>(
         &mut self,
         seed: K,
     ) -> Result<K::Value, Self::Error> {
-        seed.deserialize(&mut **self)
+        self.de.next_column_value(self.prefix, seed)
     }
 }
 
@@ -672,11 +1118,18 @@ pub enum DeserializeErrorKind {
     /// a `String` or `&str` type).
     InvalidUtf8(str::Utf8Error),
     /// This error occurs when a boolean value fails to parse.
-    ParseBool(str::ParseBoolError),
+    /// It contains the field, lossily converted to UTF-8.
+    ParseBool(String),
     /// This error occurs when an integer value fails to parse.
     ParseInt(num::ParseIntError),
     /// This error occurs when a float value fails to parse.
     ParseFloat(num::ParseFloatError),
+    /// This error occurs when a date or time value fails to parse.
+    /// `field` is the 0-based index of the field.
+    ParseDateTime { field: u64, err: String },
+    /// This error occurs when the converter of the column fails.
+    /// `field` is the 0-based index of the field.
+    Convert { field: u64, err: String },
 }
 
 impl SerdeError for DeserializeError {
@@ -713,9 +1166,11 @@ impl fmt::Display for DeserializeErrorKind {
             Unsupported(ref which) => write!(f, "unsupported deserializer method: {}", which),
             UnexpectedEndOfRow => write!(f, "{}", self.description()),
             InvalidUtf8(ref err) => err.fmt(f),
-            ParseBool(ref err) => err.fmt(f),
+            ParseBool(ref field) => write!(f, "invalid boolean '{}'", field),
             ParseInt(ref err) => err.fmt(f),
             ParseFloat(ref err) => err.fmt(f),
+            ParseDateTime { ref err, .. } => write!(f, "invalid date or time: {}", err),
+            Convert { ref err, .. } => write!(f, "conversion failed: {}", err),
         }
     }
 }
@@ -740,44 +1195,16 @@ impl DeserializeErrorKind {
             Message(_) => "deserialization error",
             Unsupported(_) => "unsupported deserializer method",
             UnexpectedEndOfRow => "expected field, but got end of row",
-            InvalidUtf8(ref err) => err.description(),
-            ParseBool(ref err) => err.description(),
-            ParseInt(ref err) => err.description(),
-            ParseFloat(ref err) => err.description(),
+            InvalidUtf8(_) => "invalid UTF-8",
+            ParseBool(_) => "invalid boolean",
+            ParseInt(_) => "invalid integer",
+            ParseFloat(_) => "invalid float",
+            ParseDateTime { .. } => "invalid date or time",
+            Convert { .. } => "conversion failed",
         }
     }
 }
 
-serde_if_integer128! {
-    fn try_positive_integer128(s: &str) -> Option<u128> {
-        s.parse().ok()
-    }
-
-    fn try_negative_integer128(s: &str) -> Option<i128> {
-        s.parse().ok()
-    }
-}
-
-fn try_positive_integer64(s: &str) -> Option<u64> {
-    s.parse().ok()
-}
-
-fn try_negative_integer64(s: &str) -> Option<i64> {
-    s.parse().ok()
-}
-
-fn try_float(s: &str) -> Option<f64> {
-    s.parse().ok()
-}
-
-fn try_positive_integer64_bytes(s: &[u8]) -> Option<u64> {
-    str::from_utf8(s).ok().and_then(|s| s.parse().ok())
-}
-
-fn try_negative_integer64_bytes(s: &[u8]) -> Option<i64> {
-    str::from_utf8(s).ok().and_then(|s| s.parse().ok())
-}
-
 serde_if_integer128! {
     fn try_positive_integer128_bytes(s: &[u8]) -> Option<u128> {
         str::from_utf8(s).ok().and_then(|s| s.parse().ok())
@@ -787,10 +1214,6 @@ serde_if_integer128! {
         str::from_utf8(s).ok().and_then(|s| s.parse().ok())
     }
 }
-
-fn try_float_bytes(s: &[u8]) -> Option<f64> {
-    str::from_utf8(s).ok().and_then(|s| s.parse().ok())
-}
 /*
 #[cfg(test)]
 mod tests {
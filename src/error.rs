@@ -27,7 +27,6 @@ impl fmt::Display for Error {
                 printer::write_ascii_escaped(f, field_data)?;
                 write!(f, ". Field ends: {:?}", field_ends)?;
             }
-            _ => unreachable!(),
         }
         Ok(())
     }
@@ -0,0 +1,265 @@
+//! Header names and the policy for building them from the header rows of the input.
+
+use core::fmt;
+use core::str;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::raw::{RawRecord, RawRecordArena, RawRecordIter};
+use crate::reader::trim_ascii;
+
+pub struct Headers {
+    pub(crate) name_data: Vec<u8>,
+    pub(crate) name_ends: Vec<usize>,
+    // The header rows as read from the input, if the header policy changed them
+    pub(crate) raw: Option<RawRecordArena>,
+    // Name to index, the first occurrence winning in case of duplicates.
+    // Built on the first lookup, and not cloned or compared.
+    index: OnceLock<HashMap<Vec<u8>, usize>>,
+    // The same with lowercase names, for case-insensitive lookups
+    index_ignore_case: OnceLock<HashMap<Vec<u8>, usize>>,
+}
+
+/// Lowercases the name like `HeaderCase::Lower`: Unicode-aware if the name
+/// is valid UTF-8, otherwise ASCII only.
+fn to_lowercase(name: &[u8]) -> Vec<u8> {
+    match str::from_utf8(name) {
+        Ok(s) => s.to_lowercase().into_bytes(),
+        Err(_) => name.to_ascii_lowercase(),
+    }
+}
+
+impl Headers {
+    /// Builds headers from the given names.
+    pub fn from_names<I>(names: I) -> Headers
+    where
+        I: IntoIterator,
+        I::Item: AsRef<[u8]>,
+    {
+        let mut name_data = Vec::new();
+        let mut name_ends = Vec::new();
+        for name in names {
+            name_data.extend_from_slice(name.as_ref());
+            name_ends.push(name_data.len());
+        }
+        Headers::from_parts(name_data, name_ends)
+    }
+
+    fn from_parts(name_data: Vec<u8>, name_ends: Vec<usize>) -> Headers {
+        Headers {
+            name_data,
+            name_ends,
+            raw: None,
+            index: OnceLock::new(),
+            index_ignore_case: OnceLock::new(),
+        }
+    }
+
+    fn build_index(&self, key: impl Fn(&[u8]) -> Vec<u8>) -> HashMap<Vec<u8>, usize> {
+        let mut index = HashMap::with_capacity(self.len());
+        for (i, name) in self.iter().enumerate() {
+            index.entry(key(name)).or_insert(i);
+        }
+        index
+    }
+
+    fn index(&self) -> &HashMap<Vec<u8>, usize> {
+        self.index.get_or_init(|| self.build_index(<[u8]>::to_vec))
+    }
+
+    pub fn iter(&self) -> RawRecordIter<'_> {
+        self.names().iter()
+    }
+
+    /// Returns the n:th name. Panics if `n` is out of bounds.
+    pub fn get(&self, n: usize) -> &[u8] {
+        let field_end = self.name_ends[n];
+        let prev_field_end = if n == 0 { 0 } else { self.name_ends[n - 1] };
+        &self.name_data[prev_field_end..field_end]
+    }
+
+    pub fn len(&self) -> usize {
+        self.name_ends.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.name_ends.is_empty()
+    }
+
+    /// Returns the index of the column with the given name.
+    /// If the name occurs more than once, the index of the first occurrence is returned.
+    pub fn index_of<N: AsRef<[u8]>>(&self, name: N) -> Option<usize> {
+        self.index().get(name.as_ref()).copied()
+    }
+
+    /// Like `index_of`, but ignores case. The names are lowercased like with
+    /// `HeaderCase::Lower`, so non-ASCII letters are folded too if the names are UTF-8.
+    pub fn index_of_ignore_case<N: AsRef<[u8]>>(&self, name: N) -> Option<usize> {
+        self.index_ignore_case
+            .get_or_init(|| self.build_index(to_lowercase))
+            .get(&to_lowercase(name.as_ref()))
+            .copied()
+    }
+
+    pub fn contains<N: AsRef<[u8]>>(&self, name: N) -> bool {
+        self.index().contains_key(name.as_ref())
+    }
+
+    /// Returns the header rows as they were read from the input,
+    /// before merging and normalizing them according to the `HeaderPolicy`.
+    pub fn raw_rows(&self) -> Vec<RawRecord<'_>> {
+        match &self.raw {
+            Some(raw) => raw.iter().collect(),
+            None => vec![self.names()],
+        }
+    }
+
+    pub(crate) fn names(&self) -> RawRecord<'_> {
+        RawRecord {
+            field_data: &self.name_data,
+            field_ends: &self.name_ends,
+        }
+    }
+}
+
+impl<'a> From<&[&'a str]> for Headers {
+    fn from(names: &[&'a str]) -> Headers {
+        Headers::from_names(names)
+    }
+}
+
+impl Clone for Headers {
+    fn clone(&self) -> Headers {
+        Headers {
+            raw: self.raw.clone(),
+            ..Headers::from_parts(self.name_data.clone(), self.name_ends.clone())
+        }
+    }
+}
+
+/// The lookup indices are derived from the names, so they aren't compared.
+impl PartialEq for Headers {
+    fn eq(&self, other: &Headers) -> bool {
+        self.name_data == other.name_data
+            && self.name_ends == other.name_ends
+            && self.raw == other.raw
+    }
+}
+
+impl Eq for Headers {}
+
+impl fmt::Debug for Headers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.names().fmt(f)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HeaderCase {
+    Unchanged,
+    Lower,
+    Upper,
+}
+
+/// Rules for building the header names from the header rows.
+///
+/// The default policy reads a single header row and keeps the names as they are.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HeaderPolicy {
+    /// The count of header rows. The names of multiple rows are joined with `separator`
+    /// into compound names, e.g. `Population` and `Male` into `Population_Male`.
+    /// Blank cells in the rows other than the last one are filled from the cell to the left,
+    /// as spreadsheets leave them blank for merged cells.
+    pub rows: usize,
+    pub separator: Vec<u8>,
+    /// Trims ASCII whitespace around the names.
+    pub trim: bool,
+    pub case: HeaderCase,
+    /// Renames duplicate names by appending a running number: `name`, `name_2`, `name_3`...
+    pub dedup: bool,
+    /// Names blank columns by appending the 1-based column number to the prefix,
+    /// e.g. `column_3`.
+    pub blank_prefix: Option<Vec<u8>>,
+}
+
+impl Default for HeaderPolicy {
+    fn default() -> Self {
+        HeaderPolicy {
+            rows: 1,
+            separator: b"_".to_vec(),
+            trim: false,
+            case: HeaderCase::Unchanged,
+            dedup: false,
+            blank_prefix: None,
+        }
+    }
+}
+
+impl HeaderPolicy {
+    /// Builds the headers from the header rows.
+    pub(crate) fn apply(&self, rows: RawRecordArena) -> Headers {
+        let width = rows.iter().map(|row| row.field_count()).max().unwrap_or(0);
+        let mut group_names: Vec<&[u8]> = vec![b""; rows.record_ends.len()];
+        let mut names: Vec<Vec<u8>> = Vec::with_capacity(width);
+        for col in 0..width {
+            let mut name = Vec::new();
+            let row_count = rows.record_ends.len();
+            for (r, row) in rows.iter().enumerate() {
+                let mut part = row.get(col).unwrap_or(b"");
+                if self.trim {
+                    part = trim_ascii(part);
+                }
+                if r + 1 < row_count {
+                    if part.is_empty() {
+                        part = group_names[r];
+                    } else {
+                        group_names[r] = part;
+                    }
+                }
+                if part.is_empty() {
+                    continue;
+                }
+                if !name.is_empty() {
+                    name.extend_from_slice(&self.separator);
+                }
+                name.extend_from_slice(part);
+            }
+            name = match self.case {
+                HeaderCase::Unchanged => name,
+                HeaderCase::Lower => to_lowercase(&name),
+                HeaderCase::Upper => match str::from_utf8(&name) {
+                    Ok(s) => s.to_uppercase().into_bytes(),
+                    Err(_) => name.to_ascii_uppercase(),
+                },
+            };
+            if name.is_empty() {
+                if let Some(prefix) = &self.blank_prefix {
+                    name.extend_from_slice(prefix);
+                    name.extend_from_slice((col + 1).to_string().as_bytes());
+                }
+            }
+            if self.dedup && names.contains(&name) {
+                let mut n = 2;
+                let deduped = loop {
+                    let mut candidate = name.clone();
+                    candidate.extend_from_slice(format!("_{}", n).as_bytes());
+                    if !names.contains(&candidate) {
+                        break candidate;
+                    }
+                    n += 1;
+                };
+                name = deduped;
+            }
+            names.push(name);
+        }
+
+        let mut headers = Headers::from_names(&names);
+        let unchanged = rows.record_ends.len() == 1
+            && rows.field_data == headers.name_data
+            && rows.field_ends == headers.name_ends;
+        if !unchanged {
+            headers.raw = Some(rows);
+        }
+        headers
+    }
+}
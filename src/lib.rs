@@ -1,5 +1,10 @@
 extern crate alloc;
-use alloc::fmt;
+
+#[cfg(feature = "arrow")]
+pub mod arrow;
+
+#[cfg(feature = "chrono")]
+pub mod datetime;
 
 #[cfg(feature = "serde")]
 mod deserialize;
@@ -7,16 +12,47 @@ mod deserialize;
 #[cfg(feature = "serde")]
 pub mod error;
 
+pub mod boolean;
 pub mod byte_arena;
+pub mod column;
+pub mod convert;
+pub mod headers;
+pub mod ndjson;
+pub mod null;
+pub mod number;
+pub mod pattern;
+pub mod pool;
 mod printer;
 mod raw;
 pub mod reader;
+pub mod record;
+pub mod schema;
+pub mod validate;
+pub mod value;
 pub mod writer;
 
-use crate::raw::{RawRecord, RawRecordIter};
+pub use boolean::{BoolFormat, BoolFormats};
+#[cfg(feature = "serde")]
+pub use byte_arena::DeserializeIter;
 pub use byte_arena::{ByteRecordArena, ByteRecordsIter};
+pub use column::{ColumnError, ColumnOptions, FromStrBytes};
+pub use convert::Converters;
+#[cfg(feature = "serde")]
+pub use deserialize::DeserializeOptions;
+pub use headers::{HeaderPolicy, Headers};
+pub use ndjson::NdjsonWriter;
+pub use null::NullTokens;
+pub use number::NumberFormat;
+pub use pool::ArenaPool;
+pub use raw::RawRecord;
 use raw::RawRecordArena;
 pub use reader::Reader;
+pub use record::{FromRecord, ToRecord, ToStrBytes};
+pub use schema::SchemaInferrer;
+#[cfg(feature = "derive")]
+pub use sleek_csv_derive::{FromRecord, ToRecord};
+pub use validate::Validator;
+pub use value::Value;
 pub use writer::Writer;
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -25,39 +61,3 @@ pub struct Position {
     pub line: u64,
     pub record: u64,
 }
-
-#[derive(Clone, Eq, PartialEq)]
-pub struct Headers {
-    name_data: Vec<u8>,
-    name_ends: Vec<usize>,
-}
-
-impl Headers {
-    pub fn iter(&self) -> RawRecordIter {
-        RawRecord {
-            field_data: &self.name_data,
-            field_ends: &self.name_ends,
-        }
-        .iter()
-    }
-
-    pub fn get(&self, n: usize) -> &[u8] {
-        let field_end = self.name_ends[n];
-        let prev_field_end = *self.name_ends.get(n-1).unwrap_or(&0);
-        &self.name_data[prev_field_end..field_end]
-    }
-
-    pub fn len(&self) -> usize {
-        self.name_ends.len()
-    }
-}
-
-impl fmt::Debug for Headers {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        RawRecord {
-            field_data: &self.name_data,
-            field_ends: &self.name_ends,
-        }
-        .fmt(f)
-    }
-}
@@ -0,0 +1,185 @@
+//! Converting records into newline-delimited JSON.
+//!
+//! Each record becomes one line: an object keyed by the header names if the arena has
+//! headers, otherwise an array. The fields are written as strings, or with type inference
+//! as the untyped `Value`s guess them. Bytes that aren't valid UTF-8 are replaced with
+//! U+FFFD, so the output is always valid JSON.
+//!
+//! In objects, the fields missing from short records are written as `null`, and the extra
+//! fields of long records are keyed `column_N` after their 1-based column, like the
+//! unnamed columns of the Arrow conversion. If a header name occurs more than once, the
+//! first column wins and the later ones are left out, like in `value::row_to_json`.
+
+use core::str;
+use std::io;
+
+use crate::raw::RawRecord;
+use crate::value::Value;
+use crate::ByteRecordArena;
+
+pub struct NdjsonWriter {
+    infer_types: bool,
+    records_written: u64,
+    line: Vec<u8>,
+    /// Whether each column repeats an earlier header name.
+    duplicates: Vec<bool>,
+}
+
+impl Default for NdjsonWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NdjsonWriter {
+    pub fn new() -> NdjsonWriter {
+        NdjsonWriter {
+            infer_types: false,
+            records_written: 0,
+            line: Vec::new(),
+            duplicates: Vec::new(),
+        }
+    }
+
+    /// Writes the fields that look like booleans or numbers as such, and empty fields as
+    /// `null`. By default all fields are written as strings.
+    pub fn set_infer_types(&mut self, infer_types: bool) {
+        self.infer_types = infer_types;
+    }
+
+    pub fn records_written(&self) -> u64 {
+        self.records_written
+    }
+
+    /// Writes the full records of the arena, one line each.
+    /// May be called repeatedly for consecutive arenas.
+    pub fn write_arena<W: io::Write>(
+        &mut self,
+        output: &mut W,
+        arena: &ByteRecordArena,
+    ) -> io::Result<()> {
+        let names = arena.headers().map(|headers| headers.names());
+        self.duplicates.clear();
+        if let Some(headers) = arena.headers() {
+            self.duplicates.extend(
+                headers
+                    .iter()
+                    .enumerate()
+                    .map(|(i, name)| headers.index_of(name) != Some(i)),
+            );
+        }
+        for record in arena.iter() {
+            self.line.clear();
+            self.write_record(record, names);
+            output.write_all(&self.line)?;
+            self.records_written += 1;
+        }
+        Ok(())
+    }
+
+    fn write_record(&mut self, record: RawRecord, names: Option<RawRecord>) {
+        let line = &mut self.line;
+        match names {
+            Some(names) => {
+                line.push(b'{');
+                let mut fields = record.iter();
+                let mut first = true;
+                for (name, &duplicate) in names.iter().zip(&self.duplicates) {
+                    let field = fields.next();
+                    if duplicate {
+                        continue;
+                    }
+                    if !first {
+                        line.push(b',');
+                    }
+                    first = false;
+                    write_string(line, name);
+                    line.push(b':');
+                    match field {
+                        Some(field) => write_field(line, field, self.infer_types),
+                        None => line.extend_from_slice(b"null"),
+                    }
+                }
+                for (col, field) in (names.field_count()..).zip(fields) {
+                    if !first {
+                        line.push(b',');
+                    }
+                    first = false;
+                    write_string(line, format!("column_{}", col + 1).as_bytes());
+                    line.push(b':');
+                    write_field(line, field, self.infer_types);
+                }
+                line.push(b'}');
+            }
+            None => {
+                line.push(b'[');
+                for (i, field) in record.iter().enumerate() {
+                    if i > 0 {
+                        line.push(b',');
+                    }
+                    write_field(line, field, self.infer_types);
+                }
+                line.push(b']');
+            }
+        }
+        line.push(b'\n');
+    }
+}
+
+fn write_field(line: &mut Vec<u8>, field: &[u8], infer_types: bool) {
+    if !infer_types {
+        write_string(line, field);
+        return;
+    }
+    match Value::infer(field) {
+        Value::Null => line.extend_from_slice(b"null"),
+        Value::Bool(b) => line.extend_from_slice(if b { b"true" } else { b"false" }),
+        Value::UInt(n) => line.extend_from_slice(n.to_string().as_bytes()),
+        Value::Int(n) => line.extend_from_slice(n.to_string().as_bytes()),
+        // JSON has no infinities or NaNs
+        Value::Float(n) if n.is_finite() => line.extend_from_slice(n.to_string().as_bytes()),
+        Value::Float(_) => line.extend_from_slice(b"null"),
+        Value::Str(_) | Value::Bytes(_) => write_string(line, field),
+    }
+}
+
+fn write_string(line: &mut Vec<u8>, s: &[u8]) {
+    line.push(b'"');
+    match str::from_utf8(s) {
+        Ok(s) => escape_str(line, s),
+        Err(_) => escape_str(line, &String::from_utf8_lossy(s)),
+    }
+    line.push(b'"');
+}
+
+fn escape_str(line: &mut Vec<u8>, s: &str) {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+    for &b in s.as_bytes() {
+        match b {
+            b'"' => line.extend_from_slice(b"\\\""),
+            b'\\' => line.extend_from_slice(b"\\\\"),
+            b'\n' => line.extend_from_slice(b"\\n"),
+            b'\r' => line.extend_from_slice(b"\\r"),
+            b'\t' => line.extend_from_slice(b"\\t"),
+            0..=0x1f => line.extend_from_slice(&[
+                b'\\',
+                b'u',
+                b'0',
+                b'0',
+                HEX[(b >> 4) as usize],
+                HEX[(b & 0xf) as usize],
+            ]),
+            _ => line.push(b),
+        }
+    }
+}
+
+#[test]
+fn test_write_string() {
+    let mut line = Vec::new();
+    write_string(&mut line, b"a\"b\\c\nd\x01\xffe");
+    assert_eq!(
+        String::from_utf8(line).unwrap(),
+        "\"a\\\"b\\\\c\\nd\\u0001\u{fffd}e\""
+    );
+}
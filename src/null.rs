@@ -0,0 +1,36 @@
+//! Null sentinels such as `NULL`, `\N` or `NA`.
+
+/// A set of field values that stand for a missing value.
+/// An empty field is always considered null.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct NullTokens {
+    tokens: Vec<Vec<u8>>,
+}
+
+impl NullTokens {
+    pub fn new<I>(tokens: I) -> NullTokens
+    where
+        I: IntoIterator,
+        I::Item: AsRef<[u8]>,
+    {
+        NullTokens {
+            tokens: tokens
+                .into_iter()
+                .map(|token| token.as_ref().to_vec())
+                .collect(),
+        }
+    }
+
+    pub fn is_null(&self, field: &[u8]) -> bool {
+        field.is_empty() || self.tokens.iter().any(|token| token == field)
+    }
+
+    /// Maps the null tokens to an empty field.
+    pub(crate) fn normalize<'a>(&self, field: &'a [u8]) -> &'a [u8] {
+        if self.is_null(field) {
+            b""
+        } else {
+            field
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! Locale-specific number formats, such as `1.234,56` or `$1,234.56`.
+
+use alloc::borrow::Cow;
+
+/// The format of the numeric fields.
+///
+/// Numbers are normalized into the Rust syntax before parsing them: an optional sign
+/// is followed by at most one of the prefixes, the digits and at most one of the suffixes.
+/// The grouping separators are removed and the decimal separator is replaced with `.`.
+/// Fields that are already in the Rust syntax are parsed without copying them.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NumberFormat {
+    pub decimal_separator: u8,
+    /// The thousands separator, e.g. `,` in `1,234.56` or `.` in `1.234,56`.
+    pub grouping_separator: Option<u8>,
+    /// Allowed prefixes such as currency symbols, e.g. `$`.
+    pub prefixes: Vec<Vec<u8>>,
+    /// Allowed suffixes such as currency symbols or units, e.g. ` €` or `%`.
+    pub suffixes: Vec<Vec<u8>>,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        NumberFormat {
+            decimal_separator: b'.',
+            grouping_separator: None,
+            prefixes: Vec::new(),
+            suffixes: Vec::new(),
+        }
+    }
+}
+
+impl NumberFormat {
+    /// Converts a numeric field into the Rust syntax.
+    pub fn normalize<'a>(&self, field: &'a [u8]) -> Cow<'a, [u8]> {
+        let (sign, unsigned) = match field {
+            [sign @ b'-', rest @ ..] | [sign @ b'+', rest @ ..] => (Some(*sign), rest),
+            _ => (None, field),
+        };
+        let mut digits = unsigned;
+        if let Some(rest) = self
+            .prefixes
+            .iter()
+            .find_map(|p| digits.strip_prefix(&p[..]))
+        {
+            digits = rest;
+        }
+        if let Some(rest) = self
+            .suffixes
+            .iter()
+            .find_map(|s| digits.strip_suffix(&s[..]))
+        {
+            digits = rest;
+        }
+
+        let rewrite = |b: u8| {
+            Some(b) == self.grouping_separator || (b == self.decimal_separator && b != b'.')
+        };
+        if !digits.iter().any(|&b| rewrite(b)) {
+            if digits.len() == unsigned.len() {
+                return Cow::Borrowed(field);
+            } else if sign.is_none() {
+                return Cow::Borrowed(digits);
+            }
+        }
+        let mut normalized = Vec::with_capacity(digits.len() + 1);
+        normalized.extend(sign);
+        for &b in digits {
+            if Some(b) == self.grouping_separator {
+                continue;
+            } else if b == self.decimal_separator {
+                normalized.push(b'.');
+            } else {
+                normalized.push(b);
+            }
+        }
+        Cow::Owned(normalized)
+    }
+}
+
+#[test]
+fn test_normalize() {
+    let european = NumberFormat {
+        decimal_separator: b',',
+        grouping_separator: Some(b'.'),
+        prefixes: Vec::new(),
+        suffixes: vec![" €".as_bytes().to_vec()],
+    };
+    assert_eq!(european.normalize(b"1.234,56"), &b"1234.56"[..]);
+    assert_eq!(european.normalize("-1.234 €".as_bytes()), &b"-1234"[..]);
+    assert!(matches!(european.normalize(b"-12"), Cow::Borrowed(_)));
+
+    let dollars = NumberFormat {
+        grouping_separator: Some(b','),
+        prefixes: vec![b"$".to_vec()],
+        ..NumberFormat::default()
+    };
+    assert_eq!(dollars.normalize(b"$1,234.5"), &b"1234.5"[..]);
+    assert_eq!(dollars.normalize(b"-$5"), &b"-5"[..]);
+    assert!(matches!(dollars.normalize(b"$5"), Cow::Borrowed(b"5")));
+}
@@ -0,0 +1,337 @@
+//! Minimal regular expressions for validating fields.
+//!
+//! A pattern always matches the whole field. Supported are literal characters, `.`,
+//! the classes `\d`, `\w`, `\s` and their negations, bracket classes such as `[a-z_]`
+//! and `[^0-9]`, and the quantifiers `*`, `+`, `?`, `{n}`, `{n,}` and `{n,m}`.
+//! Groups and alternation aren't supported. `^` and `$` are accepted at the ends
+//! but have no effect. Repetition counts are limited to 1000.
+//!
+//! Matching simulates the pattern as an NFA, so it takes time linear in the length
+//! of the field, however the field is crafted.
+
+use core::fmt;
+use core::iter::Peekable;
+use core::mem;
+use core::str::Chars;
+use std::error;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PatternError {
+    pub pattern: String,
+    pub message: &'static str,
+}
+
+impl error::Error for PatternError {}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid pattern '{}': {}", self.pattern, self.message)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Atom {
+    Any,
+    Char(char),
+    Class {
+        ranges: Vec<(char, char)>,
+        negated: bool,
+    },
+}
+
+impl Atom {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Atom::Any => true,
+            Atom::Char(expected) => c == *expected,
+            Atom::Class { ranges, negated } => {
+                ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi) != *negated
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Item {
+    atom: Atom,
+    min: usize,
+    max: usize,
+}
+
+impl Item {
+    /// The highest repetition count that is tracked. Past `min`, the count of
+    /// an unbounded item doesn't matter.
+    fn cap(&self) -> usize {
+        if self.max == usize::MAX {
+            self.min
+        } else {
+            self.max
+        }
+    }
+}
+
+const MAX_REPETITION: usize = 1000;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Pattern {
+    source: String,
+    items: Vec<Item>,
+    // The first NFA state of each item, a state per tracked repetition count.
+    // The last one is the accepting state.
+    offsets: Vec<usize>,
+}
+
+const DIGIT: &[(char, char)] = &[('0', '9')];
+const WORD: &[(char, char)] = &[('0', '9'), ('A', 'Z'), ('_', '_'), ('a', 'z')];
+const SPACE: &[(char, char)] = &[('\t', '\r'), (' ', ' ')];
+
+fn class(ranges: &[(char, char)], negated: bool) -> Atom {
+    Atom::Class {
+        ranges: ranges.to_vec(),
+        negated,
+    }
+}
+
+fn escaped(c: char) -> Atom {
+    match c {
+        'd' => class(DIGIT, false),
+        'D' => class(DIGIT, true),
+        'w' => class(WORD, false),
+        'W' => class(WORD, true),
+        's' => class(SPACE, false),
+        'S' => class(SPACE, true),
+        c => Atom::Char(c),
+    }
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    chars: Peekable<Chars<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn error<T>(&self, message: &'static str) -> Result<T, PatternError> {
+        Err(PatternError {
+            pattern: self.source.to_string(),
+            message,
+        })
+    }
+
+    fn escape(&mut self) -> Result<char, PatternError> {
+        match self.chars.next() {
+            Some(c) => Ok(c),
+            None => self.error("trailing backslash"),
+        }
+    }
+
+    fn class(&mut self) -> Result<Atom, PatternError> {
+        let negated = self.chars.next_if_eq(&'^').is_some();
+        let mut ranges = Vec::new();
+        loop {
+            let lo = match self.chars.next() {
+                Some(']') if !ranges.is_empty() => break,
+                Some('\\') => match escaped(self.escape()?) {
+                    Atom::Char(c) => c,
+                    Atom::Class {
+                        ranges: class,
+                        negated: false,
+                    } => {
+                        ranges.extend_from_slice(&class);
+                        continue;
+                    }
+                    _ => return self.error("negated class inside brackets"),
+                },
+                Some(c) => c,
+                None => return self.error("unclosed bracket"),
+            };
+            let hi = if self.chars.peek() == Some(&'-') {
+                self.chars.next();
+                match self.chars.next() {
+                    Some(']') => {
+                        ranges.push((lo, lo));
+                        ranges.push(('-', '-'));
+                        break;
+                    }
+                    Some('\\') => self.escape()?,
+                    Some(c) => c,
+                    None => return self.error("unclosed bracket"),
+                }
+            } else {
+                lo
+            };
+            if hi < lo {
+                return self.error("invalid range");
+            }
+            ranges.push((lo, hi));
+        }
+        Ok(Atom::Class { ranges, negated })
+    }
+
+    fn number(&mut self) -> Option<usize> {
+        let mut n: Option<usize> = None;
+        while let Some(digit) = self.chars.peek().and_then(|c| c.to_digit(10)) {
+            self.chars.next();
+            n = Some(
+                n.unwrap_or(0)
+                    .checked_mul(10)?
+                    .checked_add(digit as usize)?,
+            );
+        }
+        n
+    }
+
+    fn repetition(&mut self) -> Result<(usize, usize), PatternError> {
+        let min = match self.number() {
+            Some(min) => min,
+            None => return self.error("invalid repetition"),
+        };
+        let max = if self.chars.next_if_eq(&',').is_some() {
+            self.number().unwrap_or(usize::MAX)
+        } else {
+            min
+        };
+        if self.chars.next() != Some('}') || max < min {
+            return self.error("invalid repetition");
+        }
+        if min > MAX_REPETITION || (max != usize::MAX && max > MAX_REPETITION) {
+            return self.error("repetition count too large");
+        }
+        Ok((min, max))
+    }
+
+    fn parse(mut self) -> Result<Vec<Item>, PatternError> {
+        self.chars.next_if_eq(&'^');
+        let mut items = Vec::new();
+        while let Some(c) = self.chars.next() {
+            let atom = match c {
+                '$' if self.chars.peek().is_none() => break,
+                '.' => Atom::Any,
+                '[' => self.class()?,
+                '\\' => escaped(self.escape()?),
+                '*' | '+' | '?' | '{' => return self.error("quantifier without a preceding item"),
+                '(' | ')' | '|' => return self.error("groups and alternation aren't supported"),
+                c => Atom::Char(c),
+            };
+            let (min, max) = match self.chars.next_if(|c| "*+?{".contains(*c)) {
+                Some('*') => (0, usize::MAX),
+                Some('+') => (1, usize::MAX),
+                Some('?') => (0, 1),
+                Some(_) => self.repetition()?,
+                None => (1, 1),
+            };
+            items.push(Item { atom, min, max });
+        }
+        Ok(items)
+    }
+}
+
+impl Pattern {
+    pub fn new(pattern: &str) -> Result<Pattern, PatternError> {
+        let parser = Parser {
+            source: pattern,
+            chars: pattern.chars().peekable(),
+        };
+        let items = parser.parse()?;
+        let mut offsets = Vec::with_capacity(items.len() + 1);
+        let mut offset = 0;
+        for item in &items {
+            offsets.push(offset);
+            offset += item.cap() + 1;
+        }
+        offsets.push(offset);
+        Ok(Pattern {
+            source: pattern.to_string(),
+            items,
+            offsets,
+        })
+    }
+
+    /// Adds the state of having matched `count` repetitions of the item, and the states
+    /// of the following items that can be reached without consuming input.
+    fn add_state(&self, states: &mut [bool], mut item: usize, mut count: usize) {
+        loop {
+            let state = self.offsets[item] + count;
+            if states[state] {
+                return;
+            }
+            states[state] = true;
+            if item == self.items.len() || count < self.items[item].min {
+                return;
+            }
+            item += 1;
+            count = 0;
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Tells if the whole field matches. Fields that aren't valid UTF-8 never match.
+    pub fn is_match(&self, field: &[u8]) -> bool {
+        let s = match core::str::from_utf8(field) {
+            Ok(s) => s,
+            Err(_) => return false,
+        };
+        let state_count = self.offsets[self.items.len()] + 1;
+        let mut states = vec![false; state_count];
+        let mut next = vec![false; state_count];
+        self.add_state(&mut states, 0, 0);
+        for c in s.chars() {
+            next.iter_mut().for_each(|state| *state = false);
+            for (i, item) in self.items.iter().enumerate() {
+                if !item.atom.matches(c) {
+                    continue;
+                }
+                for count in 0..=item.cap() {
+                    if states[self.offsets[i] + count] && count < item.max {
+                        self.add_state(&mut next, i, (count + 1).min(item.cap()));
+                    }
+                }
+            }
+            mem::swap(&mut states, &mut next);
+            if !states.contains(&true) {
+                return false;
+            }
+        }
+        states[state_count - 1]
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+#[test]
+fn test_pattern() {
+    let code = Pattern::new(r"^[A-Z]{2}-\d{3,}$").unwrap();
+    assert!(code.is_match(b"FI-123"));
+    assert!(code.is_match(b"FI-12345"));
+    assert!(!code.is_match(b"FI-12"));
+    assert!(!code.is_match(b"fi-123"));
+    assert!(!code.is_match(b"FI-123x"));
+
+    let name = Pattern::new(r"\w+( \w+)?");
+    assert!(name.is_err());
+    let name = Pattern::new(r"\w+ ?[^\s\d]*\.?").unwrap();
+    assert!(name.is_match(b"Ada Lovelace."));
+    assert!(!name.is_match("Åsa".as_bytes()));
+    assert!(Pattern::new("a.c").unwrap().is_match("aäc".as_bytes()));
+    assert!(Pattern::new("[a-]+").unwrap().is_match(b"a-a"));
+    assert!(Pattern::new("a{2,1}").is_err());
+    assert!(Pattern::new("*a").is_err());
+    assert!(Pattern::new("a{1001}").is_err());
+
+    assert!(Pattern::new("a{2}b?").unwrap().is_match(b"aa"));
+    assert!(!Pattern::new("a{2}b?").unwrap().is_match(b"aaa"));
+    assert!(Pattern::new("a*").unwrap().is_match(b""));
+    assert!(Pattern::new("").unwrap().is_match(b""));
+    assert!(!Pattern::new("").unwrap().is_match(b"a"));
+
+    // Stacked quantifiers would take exponential time with backtracking
+    let digits = Pattern::new(r"\d*\d*\d*\d*\d*\d*\d*x").unwrap();
+    assert!(!digits.is_match(&[b'1'; 10_000]));
+    assert!(digits.is_match(b"123x"));
+}
@@ -0,0 +1,110 @@
+//! A pool of cleared `ByteRecordArena`s that retain their capacity between batches.
+//!
+//! A streaming pipeline that keeps several batches in flight can get an arena from the pool,
+//! fill it, process it and put it back, instead of allocating a fresh arena for each chunk.
+//! The pool tracks the high-water marks of the arenas returned to it, and uses them to
+//! presize new arenas when it runs empty.
+
+use core::mem;
+
+use crate::ByteRecordArena;
+
+/// The largest sizes of the arena buffers seen by a pool.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct HighWaterMarks {
+    /// Bytes of field data
+    pub field_data: usize,
+    /// Count of fields
+    pub field_ends: usize,
+    /// Count of records
+    pub record_ends: usize,
+    /// Count of arenas that were handed out at the same time
+    pub in_flight: usize,
+}
+
+pub struct ArenaPool {
+    free: Vec<ByteRecordArena>,
+    pooled_bytes: usize,
+    max_pooled_bytes: usize,
+    in_flight: usize,
+    high_water: HighWaterMarks,
+}
+
+/// The memory allocated by the arena buffers, in bytes.
+fn allocated_bytes(arena: &ByteRecordArena) -> usize {
+    arena.inner.field_data.capacity()
+        + arena.inner.field_ends.capacity() * mem::size_of::<usize>()
+        + arena.inner.record_ends.capacity() * mem::size_of::<(usize, usize)>()
+}
+
+impl ArenaPool {
+    /// Creates a pool that keeps at most `max_pooled_bytes` bytes of arena buffers
+    /// allocated. Arenas returned to a full pool are dropped.
+    pub fn new(max_pooled_bytes: usize) -> ArenaPool {
+        ArenaPool {
+            free: Vec::new(),
+            pooled_bytes: 0,
+            max_pooled_bytes,
+            in_flight: 0,
+            high_water: HighWaterMarks::default(),
+        }
+    }
+
+    /// Hands out a pristine arena. If the pool is empty, a new arena is allocated,
+    /// presized to the high-water marks seen so far.
+    pub fn get(&mut self) -> ByteRecordArena {
+        self.in_flight += 1;
+        self.high_water.in_flight = self.high_water.in_flight.max(self.in_flight);
+        if let Some(arena) = self.free.pop() {
+            self.pooled_bytes -= allocated_bytes(&arena);
+            return arena;
+        }
+        let mut arena = ByteRecordArena::new();
+        arena.inner.field_data.reserve(self.high_water.field_data);
+        arena.inner.field_ends.reserve(self.high_water.field_ends);
+        arena.inner.record_ends.reserve(self.high_water.record_ends);
+        arena
+    }
+
+    /// Takes an arena back to the pool. The arena is cleared of records, headers and
+    /// the start position, but its buffers keep their capacity.
+    pub fn put(&mut self, mut arena: ByteRecordArena) {
+        self.in_flight = self.in_flight.saturating_sub(1);
+        let hw = &mut self.high_water;
+        hw.field_data = hw.field_data.max(arena.inner.field_data.len());
+        hw.field_ends = hw.field_ends.max(arena.inner.field_ends.len());
+        hw.record_ends = hw.record_ends.max(arena.inner.record_ends.len());
+
+        arena.clear();
+        arena.headers_inner = None;
+        let bytes = allocated_bytes(&arena);
+        if self.pooled_bytes + bytes <= self.max_pooled_bytes {
+            self.pooled_bytes += bytes;
+            self.free.push(arena);
+        }
+    }
+
+    /// Returns the count of arenas waiting in the pool.
+    pub fn len(&self) -> usize {
+        self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.free.is_empty()
+    }
+
+    /// Returns the memory allocated by the arenas waiting in the pool, in bytes.
+    pub fn pooled_bytes(&self) -> usize {
+        self.pooled_bytes
+    }
+
+    pub fn high_water_marks(&self) -> HighWaterMarks {
+        self.high_water
+    }
+
+    /// Drops all the arenas waiting in the pool.
+    pub fn shrink(&mut self) {
+        self.free.clear();
+        self.pooled_bytes = 0;
+    }
+}
@@ -2,7 +2,17 @@ use crate::printer;
 use core::fmt;
 use core::ops::Range;
 
-#[derive(Clone, Eq, PartialEq)]
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+
+#[cfg(feature = "serde")]
+use crate::deserialize::{self, DeserializeOptions};
+#[cfg(feature = "serde")]
+use crate::error::Error;
+#[cfg(feature = "serde")]
+use crate::Headers;
+
+#[derive(Clone, Default, Eq, PartialEq)]
 pub struct RawRecordArena {
     pub(crate) field_data: Vec<u8>, // The unescaped data from the CSV, all fields/records concatenated
     pub(crate) field_ends: Vec<usize>, // Indices of field ends in field_data, starting from 0 for each record
@@ -23,6 +33,7 @@ pub struct RawRecordIter<'a> {
     prev_field_end: usize,
 }
 
+#[derive(Clone, Copy)]
 pub struct RawRecord<'a> {
     pub(crate) field_data: &'a [u8],
     pub(crate) field_ends: &'a [usize],
@@ -33,6 +44,13 @@ impl<'a> RawRecord<'a> {
         self.field_ends.len()
     }
 
+    /// Returns the n:th field of the record, if it exists.
+    pub fn get(&self, n: usize) -> Option<&'a [u8]> {
+        let field_end = *self.field_ends.get(n)?;
+        let prev_field_end = if n == 0 { 0 } else { self.field_ends[n - 1] };
+        Some(&self.field_data[prev_field_end..field_end])
+    }
+
     pub fn iter(&self) -> RawRecordIter<'a> {
         RawRecordIter {
             field_data: self.field_data,
@@ -41,6 +59,27 @@ impl<'a> RawRecord<'a> {
             prev_field_end: 0,
         }
     }
+
+    /// Deserializes the record by position. The headers are used for the per-column
+    /// options, and for matching by name with `DeserializeOptions::match_headers`.
+    /// The index of a deserialization error is 0.
+    #[cfg(feature = "serde")]
+    pub fn deserialize<D: Deserialize<'a>>(
+        &self,
+        headers: Option<&'a Headers>,
+    ) -> Result<D, Error> {
+        self.deserialize_with(headers, &DeserializeOptions::default())
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn deserialize_with<D: Deserialize<'a>>(
+        &self,
+        headers: Option<&'a Headers>,
+        options: &DeserializeOptions,
+    ) -> Result<D, Error> {
+        let headers = headers.map(|headers| headers.names());
+        deserialize::deserialize_raw_record(*self, 0, headers, options)
+    }
 }
 
 impl<'a> fmt::Debug for RawRecord<'a> {
@@ -96,6 +135,27 @@ impl RawRecordArena {
         }
     }
 
+    /// Appends a full record. Must not be called when the arena contains a partial record.
+    pub(crate) fn push_record(&mut self, field_data: &[u8], field_ends: &[usize]) {
+        debug_assert!(!self.is_partial());
+        self.field_data.extend_from_slice(field_data);
+        self.field_ends.extend_from_slice(field_ends);
+        self.record_ends
+            .push((self.field_data.len(), self.field_ends.len()));
+    }
+
+    /// Removes the last full record. Must not be called when the arena contains a partial record.
+    pub(crate) fn pop_record(&mut self) -> Option<(Vec<u8>, Vec<usize>)> {
+        debug_assert!(!self.is_partial());
+        self.record_ends.pop()?;
+        let (data_start, ends_start) = *self.record_ends.last().unwrap_or(&(0, 0));
+        let record = (
+            self.field_data.split_off(data_start),
+            self.field_ends.split_off(ends_start),
+        );
+        Some(record)
+    }
+
     /// Tells if RawByteRecordArea contains a partial record.
     /// If either of field_data or field_ends contains items that isn't contained in a full
     /// record indicated by record_ends, the arena is considered to contain a partial record.
@@ -168,7 +228,7 @@ impl RawRecordArena {
 
     pub fn iter(&self) -> RawRecordsIter<'_> {
         RawRecordsIter {
-            arena: &self,
+            arena: self,
             iter: 0..self.record_ends.len(),
             prev_field_data_end: 0,
             prev_field_ends_end: 0,
@@ -213,12 +273,12 @@ impl RawRecordArena {
 
 impl std::fmt::Debug for RawRecordArena {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
-        write!(f, "RawRecordArena. {} records.\n", self.record_ends.len())?;
-        write!(f, "field_data: {}\n", self.field_data.len())?;
-        write!(f, "field_ends: {}\n", self.field_ends.len())?;
+        writeln!(f, "RawRecordArena. {} records.", self.record_ends.len())?;
+        writeln!(f, "field_data: {}", self.field_data.len())?;
+        writeln!(f, "field_ends: {}", self.field_ends.len())?;
 
-        for mut record in self.iter() {
-            fmt::Debug::fmt(&mut record, f)?;
+        for record in self.iter() {
+            fmt::Debug::fmt(&record, f)?;
         }
         if self.is_partial() {
             write!(f, " + partial record: ")?;
@@ -228,7 +288,7 @@ impl std::fmt::Debug for RawRecordArena {
                 write!(f, " ...and a partial field: ")?;
                 printer::write_ascii_escaped(f, partial_field)?;
             }
-            write!(f, "\n")?;
+            writeln!(f)?;
         }
         Ok(())
     }
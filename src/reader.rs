@@ -1,6 +1,7 @@
-use std::{error, fmt};
+use std::{error, fmt, mem};
 
-use crate::{ByteRecordArena, RawRecordArena, Position};
+use crate::pattern::Pattern;
+use crate::{ByteRecordArena, HeaderPolicy, Position, RawRecordArena};
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum ReadRecordResult {
@@ -30,6 +31,198 @@ impl fmt::Display for WrongColCount {
     }
 }
 
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+/// 0-based rows and fields, rows not including header.
+/// Except for `WrongColCount`, the reader can't continue after an error,
+/// because the offending record is only partially read.
+pub enum ReadError {
+    WrongColCount(WrongColCount),
+    FieldTooLarge {
+        row_num: usize,
+        field_num: usize,
+        max_field_size: usize,
+    },
+    TooManyFields {
+        row_num: usize,
+        max_fields_per_record: usize,
+    },
+    ArenaBudgetExceeded {
+        row_num: usize,
+        arena_byte_budget: usize,
+    },
+}
+
+impl From<WrongColCount> for ReadError {
+    fn from(err: WrongColCount) -> ReadError {
+        ReadError::WrongColCount(err)
+    }
+}
+
+impl error::Error for ReadError {}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ReadError::WrongColCount(err) => err.fmt(f),
+            ReadError::FieldTooLarge {
+                row_num,
+                field_num,
+                max_field_size,
+            } => write!(
+                f,
+                "Field {} on row {} (0-based, header not in count) exceeds the maximum size of {} bytes.",
+                field_num, row_num, max_field_size
+            ),
+            ReadError::TooManyFields {
+                row_num,
+                max_fields_per_record,
+            } => write!(
+                f,
+                "Row {} (0-based, header not in count) exceeds the maximum of {} fields.",
+                row_num, max_fields_per_record
+            ),
+            ReadError::ArenaBudgetExceeded {
+                row_num,
+                arena_byte_budget,
+            } => write!(
+                f,
+                "Row {} (0-based, header not in count) exceeds the arena budget of {} bytes.",
+                row_num, arena_byte_budget
+            ),
+        }
+    }
+}
+
+/// The reason why `fill_arena` stopped reading.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum StopReason {
+    /// All of the input was consumed.
+    InputExhausted,
+    /// The arena reached the target size set by `Reader::set_arena_target_size`.
+    TargetSize,
+    /// The arena reached the record limit set by `Reader::set_arena_record_limit`.
+    RecordLimit,
+    /// Reading failed; see `FillError`.
+    Error,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct FillOutcome {
+    /// Bytes consumed from the input. Reading can be resumed from this offset.
+    pub bytes_consumed: usize,
+    /// Full records added to the arena, not including the header.
+    pub records_added: u64,
+    /// Tells if the arena ends with a partial record.
+    pub partial_record: bool,
+    /// Tells if the arena ends with a partial field.
+    pub partial_field: bool,
+    pub stop_reason: StopReason,
+}
+
+impl FillOutcome {
+    fn new(
+        arena: &ByteRecordArena,
+        bytes_consumed: usize,
+        records_added: u64,
+        stop_reason: StopReason,
+    ) -> FillOutcome {
+        FillOutcome {
+            bytes_consumed,
+            records_added,
+            partial_record: arena.is_partial(),
+            partial_field: arena.inner.get_last_partial_field().is_some(),
+            stop_reason,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct FillError {
+    pub outcome: FillOutcome,
+    pub error: ReadError,
+}
+
+impl error::Error for FillError {}
+
+impl fmt::Display for FillError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} ({} bytes consumed)",
+            self.error, self.outcome.bytes_consumed
+        )
+    }
+}
+
+/// Which values get ASCII whitespace trimmed around them.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum Trim {
+    #[default]
+    None,
+    Headers,
+    Fields,
+    All,
+}
+
+impl Trim {
+    pub(crate) fn headers(self) -> bool {
+        self == Trim::Headers || self == Trim::All
+    }
+
+    pub(crate) fn fields(self) -> bool {
+        self == Trim::Fields || self == Trim::All
+    }
+}
+
+/// `<[u8]>::trim_ascii_start`, which needs Rust 1.80.
+pub(crate) fn trim_ascii_start(bytes: &[u8]) -> &[u8] {
+    let start = bytes
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(bytes.len());
+    &bytes[start..]
+}
+
+/// `<[u8]>::trim_ascii`, which needs Rust 1.80.
+pub(crate) fn trim_ascii(bytes: &[u8]) -> &[u8] {
+    let bytes = trim_ascii_start(bytes);
+    let end = bytes
+        .iter()
+        .rposition(|b| !b.is_ascii_whitespace())
+        .map_or(0, |i| i + 1);
+    &bytes[..end]
+}
+
+/// The state of skipping the lines before the header.
+struct Preamble {
+    lines_left: usize,
+    header_marker: Option<Pattern>,
+    // The current line, collected until it can be matched against the marker.
+    line: Vec<u8>,
+    // The last line ended with `\r`, so a `\n` at the start of the next input belongs to it.
+    after_cr: bool,
+}
+
+/// A reader option that can't be set on a reader created with `Reader::from_core`.
+/// It must be configured on the csv_core reader instead.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct UnsupportedOption {
+    pub option: &'static str,
+}
+
+impl error::Error for UnsupportedOption {}
+
+impl fmt::Display for UnsupportedOption {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} can't be set on a reader created with Reader::from_core; \
+             configure the csv_core reader instead",
+            self.option
+        )
+    }
+}
+
 pub struct Reader {
     inner: csv_core::Reader,
     field_data_len: usize, // Temporarily stores arena field_data length while the Vec is overcommitted
@@ -38,6 +231,25 @@ pub struct Reader {
     ensure_col_count: bool,
     bytes_read: u64,
     records_read: u64,
+    // The unquoted fast path needs to know the exact dialect, so it's only
+    // enabled for the default csv_core configuration with a custom delimiter.
+    fast_path_delim: Option<u8>,
+    at_record_start: bool,
+    max_field_size: Option<usize>,
+    max_fields_per_record: Option<usize>,
+    arena_byte_budget: Option<usize>,
+    arena_target_size: Option<usize>,
+    arena_record_limit: Option<usize>,
+    preamble: Option<Preamble>,
+    comment: Option<u8>,
+    trailer_marker: Option<Vec<u8>>,
+    // A record matching the trailer marker, held back until it's known whether it's the last one
+    pending_trailer: Option<(Vec<u8>, Vec<usize>)>,
+    // A record read after a held back record filled the arena, added first to the next arena
+    overflow_record: Option<(Vec<u8>, Vec<usize>)>,
+    header_policy: HeaderPolicy,
+    header_rows: RawRecordArena,
+    trim: Trim,
 }
 
 impl Reader {
@@ -50,6 +262,21 @@ impl Reader {
             ensure_col_count: true,
             bytes_read: 0,
             records_read: 0,
+            fast_path_delim: Some(delim),
+            at_record_start: false,
+            max_field_size: None,
+            max_fields_per_record: None,
+            arena_byte_budget: None,
+            arena_target_size: None,
+            arena_record_limit: None,
+            preamble: None,
+            comment: None,
+            trailer_marker: None,
+            pending_trailer: None,
+            overflow_record: None,
+            header_policy: HeaderPolicy::default(),
+            header_rows: RawRecordArena::new(),
+            trim: Trim::None,
         }
     }
 
@@ -62,7 +289,314 @@ impl Reader {
             ensure_col_count: true,
             bytes_read: 0,
             records_read: 0,
+            fast_path_delim: None,
+            at_record_start: false,
+            max_field_size: None,
+            max_fields_per_record: None,
+            arena_byte_budget: None,
+            arena_target_size: None,
+            arena_record_limit: None,
+            preamble: None,
+            comment: None,
+            trailer_marker: None,
+            pending_trailer: None,
+            overflow_record: None,
+            header_policy: HeaderPolicy::default(),
+            header_rows: RawRecordArena::new(),
+            trim: Trim::None,
+        }
+    }
+
+    /// Allows records with differing column counts, e.g. rows of different kinds that are
+    /// deserialized into the variants of an enum. By default, all records must have as many
+    /// fields as the headers, or as the first record, or reading fails with
+    /// `ReadError::WrongColCount`.
+    pub fn set_flexible(&mut self, flexible: bool) {
+        self.ensure_col_count = !flexible;
+    }
+
+    /// Sets the maximum size of a single field in bytes.
+    /// A larger field makes `fill_arena` fail with `ReadError::FieldTooLarge`.
+    pub fn set_max_field_size(&mut self, max_field_size: Option<usize>) {
+        self.max_field_size = max_field_size;
+    }
+
+    /// Sets the maximum count of fields in a single record.
+    /// A record with more fields makes `fill_arena` fail with `ReadError::TooManyFields`.
+    pub fn set_max_fields_per_record(&mut self, max_fields_per_record: Option<usize>) {
+        self.max_fields_per_record = max_fields_per_record;
+    }
+
+    /// Sets the maximum size of the field data of an arena in bytes.
+    /// If a record doesn't fit in the budget, `fill_arena` fails with `ReadError::ArenaBudgetExceeded`.
+    pub fn set_arena_byte_budget(&mut self, arena_byte_budget: Option<usize>) {
+        self.arena_byte_budget = arena_byte_budget;
+    }
+
+    /// Sets the target size of the field data of an arena in bytes.
+    /// `fill_arena` stops after the first full record that makes the arena reach the target size,
+    /// with `StopReason::TargetSize`.
+    pub fn set_arena_target_size(&mut self, arena_target_size: Option<usize>) {
+        self.arena_target_size = arena_target_size;
+    }
+
+    /// Sets the maximum count of full records in an arena.
+    /// `fill_arena` stops when the arena reaches the limit, with `StopReason::RecordLimit`,
+    /// leaving the rest of the input for the next batch.
+    pub fn set_arena_record_limit(&mut self, arena_record_limit: Option<usize>) {
+        self.arena_record_limit = arena_record_limit;
+    }
+
+    fn preamble_mut(&mut self) -> &mut Preamble {
+        self.preamble.get_or_insert_with(|| Preamble {
+            lines_left: 0,
+            header_marker: None,
+            line: Vec::new(),
+            after_cr: false,
+        })
+    }
+
+    /// Skips the first `lines` lines of the input before reading the header.
+    /// The lines are skipped as raw text, so they may contain unbalanced quotes.
+    /// Like with the records, the lines may end with `\n`, `\r\n` or `\r`.
+    pub fn set_skip_lines(&mut self, lines: usize) {
+        self.preamble_mut().lines_left = lines;
+    }
+
+    /// Skips all lines up to the first line that matches the `marker` pattern as a whole,
+    /// e.g. `ID,.*` for a header starting with `ID,`. Lines that aren't valid UTF-8
+    /// don't match. The matching line is read as the header, or as the first record
+    /// if the reader doesn't skip headers. If `set_skip_lines` is also set, the search
+    /// for the marker starts after the skipped lines.
+    pub fn set_header_marker(&mut self, marker: Option<Pattern>) {
+        self.preamble_mut().header_marker = marker;
+    }
+
+    /// Sets the comment byte. Lines starting with it are skipped.
+    ///
+    /// Must be called before reading. Fails for readers created with `from_core`,
+    /// which must configure the comment byte on the csv_core reader instead.
+    pub fn set_comment(&mut self, comment: Option<u8>) -> Result<(), UnsupportedOption> {
+        let delim = self.fast_path_delim.ok_or(UnsupportedOption {
+            option: "the comment byte",
+        })?;
+        let line = self.inner.line();
+        self.inner = csv_core::ReaderBuilder::new()
+            .delimiter(delim)
+            .comment(comment)
+            .build();
+        self.inner.set_line(line);
+        self.comment = comment;
+        Ok(())
+    }
+
+    /// Sets the policy for building the header names from the header rows.
+    /// Has an effect only if the reader reads headers.
+    pub fn set_header_policy(&mut self, header_policy: HeaderPolicy) {
+        assert!(
+            header_policy.rows > 0,
+            "the header policy must have at least one row"
+        );
+        self.header_policy = header_policy;
+    }
+
+    /// Sets the trimming of the headers and fields. The trimmed values are stored
+    /// in the arena, so trimming costs nothing later on.
+    pub fn set_trim(&mut self, trim: Trim) {
+        self.trim = trim;
+    }
+
+    /// Sets a marker for the trailer record, e.g. `TOTAL` for a `TOTAL,12345` line.
+    /// If the last record of the input has the marker as its first field, it isn't added
+    /// to the records of the arena. It's captured separately, see `ByteRecordArena::trailer`,
+    /// and it's exempt from the column count check. Records with the marker that are
+    /// followed by other records are read as usual.
+    ///
+    /// As a record with the marker can only be known to be the last one at the end of
+    /// the input, it's held back by the reader until the next record or a call to `finish`.
+    pub fn set_trailer_marker(&mut self, marker: Option<&[u8]>) {
+        self.trailer_marker = marker.map(|m| m.to_vec());
+    }
+
+    /// Skips the preamble lines at the start of the input.
+    /// Returns the count of bytes skipped and the beginning of the header line
+    /// that was consumed already from earlier inputs while matching the header marker.
+    fn skip_preamble(&mut self, input: &[u8]) -> (usize, Vec<u8>) {
+        let mut pos = 0;
+        let mut newlines = 0;
+        let result = loop {
+            let preamble = match &mut self.preamble {
+                Some(preamble) => preamble,
+                None => break (pos, Vec::new()),
+            };
+            if preamble.after_cr && pos < input.len() {
+                preamble.after_cr = false;
+                if input[pos] == b'\n' {
+                    pos += 1;
+                    newlines += 1;
+                    continue;
+                }
+            }
+            let rest = &input[pos..];
+            // The line numbers count `\n`s, like csv_core does
+            let line_end = memchr::memchr2(b'\r', b'\n', rest);
+            if preamble.lines_left > 0 {
+                match line_end {
+                    Some(line_end) => {
+                        pos += line_end + 1;
+                        newlines += u64::from(rest[line_end] == b'\n');
+                        preamble.after_cr = rest[line_end] == b'\r';
+                        preamble.lines_left -= 1;
+                        continue;
+                    }
+                    None => break (input.len(), Vec::new()),
+                }
+            }
+            let marker = match &preamble.header_marker {
+                Some(marker) => marker,
+                // Wait for the next input to tell if the last line ended with `\r\n`
+                None if preamble.after_cr => break (pos, Vec::new()),
+                None => {
+                    self.preamble = None;
+                    continue;
+                }
+            };
+            let part = &rest[..line_end.unwrap_or(rest.len())];
+            preamble.line.extend_from_slice(part);
+            let line_end = match line_end {
+                Some(line_end) => line_end,
+                // The input ended before the line could be matched
+                None => break (input.len(), Vec::new()),
+            };
+            if marker.is_match(&preamble.line) {
+                let mut held = mem::take(&mut preamble.line);
+                held.truncate(held.len() - part.len());
+                self.preamble = None;
+                break (pos, held);
+            }
+            preamble.line.clear();
+            pos += line_end + 1;
+            newlines += u64::from(rest[line_end] == b'\n');
+            preamble.after_cr = rest[line_end] == b'\r';
+        };
+        self.inner.set_line(self.inner.line() + newlines);
+        result
+    }
+
+    /// Trims the fields of the last record in place, moving the field data
+    /// to close the gaps left by the trimmed whitespace.
+    fn trim_last_record(&mut self, arena: &mut RawRecordArena) {
+        let record_count = arena.record_ends.len();
+        let (data_start, ends_start) = match record_count {
+            0 => return,
+            1 => (0, 0),
+            _ => arena.record_ends[record_count - 2],
+        };
+        let ends_end = arena.record_ends[record_count - 1].1;
+        let mut write_pos = data_start;
+        let mut field_start = data_start;
+        for field_end in &mut arena.field_ends[ends_start..ends_end] {
+            let field = &arena.field_data[field_start..data_start + *field_end];
+            let trimmed_start = field_start + field.len() - trim_ascii_start(field).len();
+            let trimmed_len = trim_ascii(field).len();
+            field_start = data_start + *field_end;
+            arena
+                .field_data
+                .copy_within(trimmed_start..trimmed_start + trimmed_len, write_pos);
+            write_pos += trimmed_len;
+            *field_end = write_pos - data_start;
+        }
+        arena.record_ends[record_count - 1].0 = write_pos;
+        self.field_data_len = write_pos;
+    }
+
+    /// Tells if the first field of the last record of the arena is the trailer marker.
+    fn has_trailer_marker(&self, arena: &RawRecordArena) -> bool {
+        let marker = match &self.trailer_marker {
+            Some(marker) => marker,
+            None => return false,
+        };
+        let record_count = arena.record_ends.len();
+        let (data_start, ends_start) = match record_count {
+            0 => return false,
+            1 => (0, 0),
+            _ => arena.record_ends[record_count - 2],
+        };
+        let ends_end = arena.record_ends[record_count - 1].1;
+        if ends_start == ends_end {
+            return false;
+        }
+        let first_field_end = data_start + arena.field_ends[ends_start];
+        &arena.field_data[data_start..first_field_end] == marker.as_slice()
+    }
+
+    /// Appends a full record to the overcommitted arena.
+    fn restore_record(
+        &mut self,
+        arena: &mut RawRecordArena,
+        field_data: &[u8],
+        field_ends: &[usize],
+    ) {
+        let data_end = self.field_data_len + field_data.len();
+        let ends_end = self.field_ends_len + field_ends.len();
+        if arena.field_data.len() < data_end {
+            arena.field_data.resize(data_end, 0);
+        }
+        if arena.field_ends.len() < ends_end {
+            arena.field_ends.resize(ends_end, 0);
+        }
+        arena.field_data[self.field_data_len..data_end].copy_from_slice(field_data);
+        arena.field_ends[self.field_ends_len..ends_end].copy_from_slice(field_ends);
+        self.field_data_len = data_end;
+        self.field_ends_len = ends_end;
+        arena.record_ends.push((data_end, ends_end));
+    }
+
+    /// Ends the input. Completes the partial record of the arena, if any, and moves
+    /// the last record to `ByteRecordArena::trailer` if it has the trailer marker.
+    ///
+    /// The records held back by the reader are added to the arena, and they and the completed
+    /// record are checked for the column count like in `fill_arena`.
+    pub fn finish(&mut self, arena: &mut ByteRecordArena) -> Result<(), ReadError> {
+        let mut expected_col_count = arena
+            .headers()
+            .map(|h| h.len())
+            .or_else(|| arena.iter().next().map(|record| record.field_count()));
+        let record_count = arena.record_count();
+        let res = self.finish_records(arena, &mut expected_col_count);
+        self.records_read += arena.record_count() - record_count;
+        res
+    }
+
+    fn finish_records(
+        &mut self,
+        arena: &mut ByteRecordArena,
+        expected_col_count: &mut Option<usize>,
+    ) -> Result<(), ReadError> {
+        self.add_overflow_record(arena, expected_col_count)?;
+        let mut last = self.pending_trailer.take();
+        if arena.is_partial() {
+            arena.complete_partial();
+            let partial = arena.inner.pop_record().expect("completed record");
+            if let Some((field_data, field_ends)) = last.take() {
+                arena.inner.push_record(&field_data, &field_ends);
+                let row_num = arena.inner.record_ends.len() - 1;
+                self.check_col_count(field_ends.len(), expected_col_count, row_num)?;
+            }
+            arena.inner.push_record(&partial.0, &partial.1);
+            if self.has_trailer_marker(&arena.inner) {
+                last = arena.inner.pop_record();
+            } else {
+                let row_num = arena.inner.record_ends.len() - 1;
+                self.check_col_count(partial.1.len(), expected_col_count, row_num)?;
+            }
+        }
+        if let Some((field_data, field_ends)) = last {
+            let mut trailer = RawRecordArena::new();
+            trailer.push_record(&field_data, &field_ends);
+            arena.trailer_inner = Some(trailer);
         }
+        Ok(())
     }
 
     fn arena_overcommit(&mut self, arena: &mut RawRecordArena, input_size: usize) {
@@ -71,9 +605,11 @@ impl Reader {
         self.field_data_len = arena.field_data.len();
         self.field_ends_len = arena.field_ends.len();
 
-        arena
-            .field_data
-            .resize(arena.field_data.len() + input_size, 0);
+        let mut field_data_len = arena.field_data.len() + input_size;
+        if let Some(budget) = self.arena_byte_budget {
+            field_data_len = field_data_len.min(budget.max(arena.field_data.len()));
+        }
+        arena.field_data.resize(field_data_len, 0);
 
         // We don't know the exact count of the fields,
         // but let's approximate with each field having 8 bytes at average
@@ -103,48 +639,150 @@ impl Reader {
         arena.field_ends.resize(arena.field_ends.len() * 2, 0);
     }
 
-    fn scrape_headers(&mut self, arena: &mut RawRecordArena) -> crate::Headers {
-        let (header_data_len, header_ends_len) = arena.record_ends.pop().expect("");
-        let headers = crate::Headers {
-            name_data: arena.field_data[..header_data_len].to_owned(),
-            name_ends: arena.field_ends[..header_ends_len].to_owned(),
+    fn check_col_count(
+        &self,
+        col_count: usize,
+        expected_col_count: &mut Option<usize>,
+        row_num: usize,
+    ) -> Result<(), ReadError> {
+        if !self.ensure_col_count {
+            return Ok(());
+        }
+        match *expected_col_count {
+            Some(expected_col_count) if col_count != expected_col_count => {
+                Err(ReadError::WrongColCount(WrongColCount {
+                    row_num,
+                    col_count,
+                    expected_col_count,
+                }))
+            }
+            Some(_) => Ok(()),
+            None => {
+                *expected_col_count = Some(col_count);
+                Ok(())
+            }
+        }
+    }
+
+    /// Tells if an arena with the given count of records and field data length
+    /// has reached the target size or the record limit.
+    fn arena_full(&self, record_count: usize, field_data_len: usize) -> Option<StopReason> {
+        if let Some(target) = self.arena_target_size {
+            if field_data_len >= target {
+                return Some(StopReason::TargetSize);
+            }
+        }
+        if let Some(limit) = self.arena_record_limit {
+            if record_count >= limit {
+                return Some(StopReason::RecordLimit);
+            }
+        }
+        None
+    }
+
+    /// Adds the record that didn't fit in the previous arena, if any.
+    /// Returns the stop reason if the arena is full after it.
+    fn add_overflow_record(
+        &mut self,
+        arena: &mut ByteRecordArena,
+        expected_col_count: &mut Option<usize>,
+    ) -> Result<Option<StopReason>, ReadError> {
+        let (field_data, field_ends) = match self.overflow_record.take() {
+            Some(record) => record,
+            None => return Ok(None),
         };
+        arena.inner.push_record(&field_data, &field_ends);
+        let row_num = arena.inner.record_ends.len() - 1;
+        self.check_col_count(field_ends.len(), expected_col_count, row_num)?;
+        Ok(self.arena_full(arena.inner.record_ends.len(), arena.inner.field_data.len()))
+    }
 
+    /// Removes the last record from the arena and rolls back
+    /// to the field_data and field_ends lengths before it.
+    fn scrape_record(&mut self, arena: &mut RawRecordArena) -> (Vec<u8>, Vec<usize>) {
+        let (record_data_len, record_ends_len) = arena.record_ends.pop().expect("");
         let (prev_field_data_len, prev_field_ends_len) =
-            arena.record_ends.last().unwrap_or(&(0, 0));
-        self.field_data_len = *prev_field_data_len;
-        self.field_ends_len = *prev_field_ends_len;
+            *arena.record_ends.last().unwrap_or(&(0, 0));
+        let record = (
+            arena.field_data[prev_field_data_len..record_data_len].to_owned(),
+            arena.field_ends[prev_field_ends_len..record_ends_len].to_owned(),
+        );
+        self.field_data_len = prev_field_data_len;
+        self.field_ends_len = prev_field_ends_len;
 
-        headers
+        record
     }
 
-    pub fn fill_arena<'a>(
+    /// Reads records from the input into the arena.
+    /// The outcome tells how much of the input was consumed and why the reading stopped.
+    /// On error, the outcome is included in the error, so that the caller can resume
+    /// reading after the offending record in case of `ReadError::WrongColCount`.
+    pub fn fill_arena(
         &mut self,
-        mut input: &'a [u8],
+        mut input: &[u8],
         arena_outer: &mut ByteRecordArena,
-    ) -> Result<(), WrongColCount> {
+    ) -> Result<FillOutcome, FillError> {
         let mut expected_col_count = arena_outer.headers().map(|h| h.len());
-        // The empty case must be checked because the CSV core reader
-        // considers (erroneously) a record having ended if an empty slice is passed in.
-        if input.is_empty() {
-            return Ok(());
+        if let Some(limit) = self.arena_record_limit {
+            if arena_outer.record_count() >= limit as u64 {
+                return Ok(FillOutcome::new(arena_outer, 0, 0, StopReason::RecordLimit));
+            }
         }
+        let arena_orig_record_count = arena_outer.record_count();
+
+        let (preamble_bytes, held) = self.skip_preamble(input);
+        input = &input[preamble_bytes..];
+        // The beginning of the header line was consumed with the earlier inputs,
+        // so it's read again before the rest of the input.
+        let joined;
+        let mut input = if held.is_empty() {
+            input
+        } else {
+            joined = [held.as_slice(), input].concat();
+            &joined[..]
+        };
+        let bytes_to_parse = input.len();
 
-        if arena_outer.start_pos.is_none() {
+        if arena_outer.start_pos.is_none() && (!input.is_empty() || self.overflow_record.is_some())
+        {
             // TODO: determine the exact semantics what counts as "clear" and "empty"
             //       when should we rewrite the start_pos? should we track if it's
             //       initialized or not?
-            // For now, restart if fill_arena is called for an arena that contains no records
+            // For now, restart if fill_arena is called for an arena that contains no records.
+            // The position is taken after the skipped preamble.
             let start_pos = Position {
                 line: self.inner.line(),
-                byte: self.bytes_read,
+                byte: self.bytes_read + preamble_bytes as u64 - held.len() as u64,
                 record: self.records_read,
             };
             arena_outer.start_pos = Some(start_pos);
         }
 
-        let input_total_bytes = input.len();
-        let arena_orig_record_count = arena_outer.record_count();
+        let overflow = self.add_overflow_record(arena_outer, &mut expected_col_count);
+        // The empty case must be checked because the CSV core reader
+        // considers (erroneously) a record having ended if an empty slice is passed in.
+        if input.is_empty() || !matches!(overflow, Ok(None)) {
+            let records_added = arena_outer.record_count() - arena_orig_record_count;
+            self.bytes_read += preamble_bytes as u64;
+            self.records_read += records_added;
+            return match overflow {
+                Ok(stop_reason) => Ok(FillOutcome::new(
+                    arena_outer,
+                    preamble_bytes,
+                    records_added,
+                    stop_reason.unwrap_or(StopReason::InputExhausted),
+                )),
+                Err(error) => Err(FillError {
+                    outcome: FillOutcome::new(
+                        arena_outer,
+                        preamble_bytes,
+                        records_added,
+                        StopReason::Error,
+                    ),
+                    error,
+                }),
+            };
+        }
 
         let arena = &mut arena_outer.inner;
         self.arena_overcommit(arena, input.len());
@@ -152,46 +790,117 @@ impl Reader {
             let (result, unparsed) = self.read_record(input, arena);
             input = unparsed;
             match result {
-                ReadRecordResult::NeedsMoreInput | ReadRecordResult::NeedsMoreInputOrEof => {
+                Err(err) => break Err(err),
+                Ok(ReadRecordResult::NeedsMoreInput)
+                | Ok(ReadRecordResult::NeedsMoreInputOrEof) => {
                     debug_assert!(input.is_empty());
-                    break Ok(());
+                    break Ok(StopReason::InputExhausted);
                 }
-                ReadRecordResult::Record(col_count) => {
-                    if self.ensure_col_count {
-                        if let Some(expected_col_count) = expected_col_count {
-                            if col_count != expected_col_count {
-                                break Err(WrongColCount {
-                                    row_num: arena.record_ends.len() - 1,
-                                    col_count: col_count,
-                                    expected_col_count: expected_col_count,
-                                });
-                            }
-                        } else {
-                            expected_col_count = Some(col_count)
-                        }
+                Ok(ReadRecordResult::Record(col_count)) => {
+                    let trim = if self.skip_header {
+                        self.trim.headers()
+                    } else {
+                        self.trim.fields()
+                    };
+                    if trim {
+                        self.trim_last_record(arena);
                     }
                     // If the reader must skip header, we remove the newly read record,
-                    // save it as a header and roll back
+                    // save it as a header row and roll back
                     // to the field_data and field_ends lengths.
                     if self.skip_header {
-                        self.skip_header = false;
-                        assert!(arena_outer.headers_inner.is_none());
-                        arena_outer.headers_inner = Some(self.scrape_headers(arena));
+                        let (field_data, field_ends) = self.scrape_record(arena);
+                        self.header_rows.push_record(&field_data, &field_ends);
+                        if self.header_rows.record_ends.len() >= self.header_policy.rows {
+                            self.skip_header = false;
+                            assert!(arena_outer.headers_inner.is_none());
+                            let rows = mem::take(&mut self.header_rows);
+                            let headers = self.header_policy.apply(rows);
+                            expected_col_count = Some(headers.len());
+                            arena_outer.headers_inner = Some(headers);
+                        }
+                        continue;
+                    }
+                    let is_trailer = self.has_trailer_marker(arena);
+                    if is_trailer || self.pending_trailer.is_some() {
+                        let (field_data, field_ends) = self.scrape_record(arena);
+                        // The held back record wasn't the last one after all
+                        if let Some((data, ends)) = self.pending_trailer.take() {
+                            self.restore_record(arena, &data, &ends);
+                            let row_num = arena.record_ends.len() - 1;
+                            let checked =
+                                self.check_col_count(ends.len(), &mut expected_col_count, row_num);
+                            if let Err(err) = checked {
+                                self.restore_record(arena, &field_data, &field_ends);
+                                break Err(err);
+                            }
+                            if let Some(stop_reason) =
+                                self.arena_full(arena.record_ends.len(), self.field_data_len)
+                            {
+                                if is_trailer {
+                                    self.pending_trailer = Some((field_data, field_ends));
+                                } else {
+                                    self.overflow_record = Some((field_data, field_ends));
+                                }
+                                break Ok(stop_reason);
+                            }
+                        }
+                        if is_trailer {
+                            self.pending_trailer = Some((field_data, field_ends));
+                            continue;
+                        }
+                        self.restore_record(arena, &field_data, &field_ends);
+                    }
+                    let row_num = arena.record_ends.len() - 1;
+                    if let Err(err) =
+                        self.check_col_count(col_count, &mut expected_col_count, row_num)
+                    {
+                        break Err(err);
+                    }
+                    if let Some(stop_reason) =
+                        self.arena_full(arena.record_ends.len(), self.field_data_len)
+                    {
+                        break Ok(stop_reason);
                     }
                 }
             }
         };
         self.arena_shrink_back(arena);
-        self.bytes_read += (input_total_bytes - input.len()) as u64;
-        self.records_read += arena_outer.record_count() - arena_orig_record_count;
-        res
+        let bytes_consumed =
+            (preamble_bytes + bytes_to_parse - input.len()).saturating_sub(held.len());
+        let records_added = arena_outer.record_count() - arena_orig_record_count;
+        self.bytes_read += bytes_consumed as u64;
+        self.records_read += records_added;
+        match res {
+            Ok(stop_reason) => Ok(FillOutcome::new(
+                arena_outer,
+                bytes_consumed,
+                records_added,
+                stop_reason,
+            )),
+            Err(error) => Err(FillError {
+                outcome: FillOutcome::new(
+                    arena_outer,
+                    bytes_consumed,
+                    records_added,
+                    StopReason::Error,
+                ),
+                error,
+            }),
+        }
     }
 
     fn read_record<'a>(
         &mut self,
         mut input: &'a [u8],
         arena: &mut RawRecordArena,
-    ) -> (ReadRecordResult, &'a [u8]) {
+    ) -> (Result<ReadRecordResult, ReadError>, &'a [u8]) {
+        if let (Some(delim), true) = (self.fast_path_delim, self.at_record_start) {
+            match self.read_record_unquoted(input, arena, delim) {
+                Ok((res, unparsed)) => return (Ok(res), unparsed),
+                Err(rest) => input = rest,
+            }
+        }
         let res = loop {
             let (inner_res, bytes_in, bytes_out, ends_out) = self.inner.read_record(
                 input,
@@ -206,24 +915,165 @@ impl Reader {
             self.field_data_len += bytes_out;
             self.field_ends_len += ends_out;
 
+            if let Err(err) = self.check_limits(arena, self.field_ends_len - ends_out) {
+                break Err(err);
+            }
+
             match inner_res {
                 // Adds capacity and tries again
-                csv_core::ReadRecordResult::OutputFull => self.arena_extend_field_data(arena),
+                csv_core::ReadRecordResult::OutputFull => match self.arena_byte_budget {
+                    Some(budget) if arena.field_data.len() >= budget => {
+                        break Err(ReadError::ArenaBudgetExceeded {
+                            row_num: arena.record_ends.len(),
+                            arena_byte_budget: budget,
+                        });
+                    }
+                    Some(budget) => {
+                        let new_len = (arena.field_data.len() * 2).min(budget);
+                        arena.field_data.resize(new_len, 0);
+                    }
+                    None => self.arena_extend_field_data(arena),
+                },
                 csv_core::ReadRecordResult::OutputEndsFull => self.arena_extend_field_ends(arena),
 
                 // Returns with status
-                csv_core::ReadRecordResult::InputEmpty => break ReadRecordResult::NeedsMoreInput,
-                csv_core::ReadRecordResult::End => break ReadRecordResult::NeedsMoreInputOrEof,
+                csv_core::ReadRecordResult::InputEmpty => {
+                    // csv_core might be in the middle of a record, so the fast path can't be used
+                    self.at_record_start = false;
+                    break Ok(ReadRecordResult::NeedsMoreInput);
+                }
+                csv_core::ReadRecordResult::End => break Ok(ReadRecordResult::NeedsMoreInputOrEof),
                 csv_core::ReadRecordResult::Record => {
-                    let last_record_end_field_end = arena.record_ends.last().unwrap_or(&(0, 0)).1;
-                    let col_count = self.field_ends_len - last_record_end_field_end;
-                    arena
-                        .record_ends
-                        .push((self.field_data_len, self.field_ends_len));
-                    break ReadRecordResult::Record(col_count);
+                    self.at_record_start = true;
+                    break Ok(self.terminate_record(arena));
                 }
             };
         };
         (res, input)
     }
+
+    /// Checks the field size and field count limits of the current record.
+    /// The fields ending at `new_field_ends_from` or later are new since the last check.
+    fn check_limits(
+        &self,
+        arena: &RawRecordArena,
+        new_field_ends_from: usize,
+    ) -> Result<(), ReadError> {
+        let &(record_data_start, record_ends_start) = arena.record_ends.last().unwrap_or(&(0, 0));
+        let row_num = arena.record_ends.len();
+        if let Some(max_fields_per_record) = self.max_fields_per_record {
+            if self.field_ends_len - record_ends_start > max_fields_per_record {
+                return Err(ReadError::TooManyFields {
+                    row_num,
+                    max_fields_per_record,
+                });
+            }
+        }
+        if let Some(max_field_size) = self.max_field_size {
+            let new_field_ends_from = new_field_ends_from.max(record_ends_start);
+            let mut prev_field_end = if new_field_ends_from > record_ends_start {
+                arena.field_ends[new_field_ends_from - 1]
+            } else {
+                0
+            };
+            // The completed fields and the one in progress
+            let partial_field_end = self.field_data_len - record_data_start;
+            let field_ends = arena.field_ends[new_field_ends_from..self.field_ends_len]
+                .iter()
+                .chain(Some(&partial_field_end));
+            for (i, &field_end) in field_ends.enumerate() {
+                if field_end - prev_field_end > max_field_size {
+                    return Err(ReadError::FieldTooLarge {
+                        row_num,
+                        field_num: new_field_ends_from - record_ends_start + i,
+                        max_field_size,
+                    });
+                }
+                prev_field_end = field_end;
+            }
+        }
+        Ok(())
+    }
+
+    fn terminate_record(&mut self, arena: &mut RawRecordArena) -> ReadRecordResult {
+        let last_record_end_field_end = arena.record_ends.last().unwrap_or(&(0, 0)).1;
+        let col_count = self.field_ends_len - last_record_end_field_end;
+        arena
+            .record_ends
+            .push((self.field_data_len, self.field_ends_len));
+        ReadRecordResult::Record(col_count)
+    }
+
+    /// A fast path for reading a record that is fully contained in the input
+    /// and doesn't contain quotes. The line is scanned with `memchr` and the fields
+    /// are bulk-copied into the arena. The output is identical to that of csv_core.
+    ///
+    /// Must be called only at a record boundary. Returns the unconsumed input
+    /// as an error if the record must be read by csv_core instead.
+    fn read_record_unquoted<'a>(
+        &mut self,
+        mut input: &'a [u8],
+        arena: &mut RawRecordArena,
+        delim: u8,
+    ) -> Result<(ReadRecordResult, &'a [u8]), &'a [u8]> {
+        // csv_core skips empty lines at the start of a record; so do we.
+        let start = input
+            .iter()
+            .position(|&b| b != b'\r' && b != b'\n')
+            .unwrap_or(input.len());
+        let newlines = memchr::memchr_iter(b'\n', &input[..start]).count();
+        self.inner.set_line(self.inner.line() + newlines as u64);
+        input = &input[start..];
+        if input.is_empty() {
+            return Ok((ReadRecordResult::NeedsMoreInput, input));
+        }
+        if self.comment.is_some() && self.comment == input.first().copied() {
+            return Err(input);
+        }
+
+        let line_end = match memchr::memchr2(b'\r', b'\n', input) {
+            Some(line_end) => line_end,
+            None => return Err(input),
+        };
+        let line = &input[..line_end];
+        if memchr::memchr(b'"', line).is_some() {
+            return Err(input);
+        }
+
+        let field_count = memchr::memchr_iter(delim, line).count() + 1;
+        // If the record might hit a limit, csv_core reads it and reports the error.
+        let over_limit = |limit: Option<usize>, size| limit.is_some_and(|limit| size > limit);
+        if over_limit(self.max_field_size, line.len())
+            || over_limit(self.max_fields_per_record, field_count)
+            || over_limit(self.arena_byte_budget, self.field_data_len + line.len())
+        {
+            return Err(input);
+        }
+        if arena.field_data.len() < self.field_data_len + line.len() {
+            arena.field_data.resize(self.field_data_len + line.len(), 0);
+        }
+        if arena.field_ends.len() < self.field_ends_len + field_count {
+            arena
+                .field_ends
+                .resize(self.field_ends_len + field_count, 0);
+        }
+
+        let record_start = arena.record_ends.last().unwrap_or(&(0, 0)).0;
+        let mut field_start = 0;
+        for field_end in memchr::memchr_iter(delim, line).chain(Some(line.len())) {
+            let field = &line[field_start..field_end];
+            arena.field_data[self.field_data_len..self.field_data_len + field.len()]
+                .copy_from_slice(field);
+            self.field_data_len += field.len();
+            arena.field_ends[self.field_ends_len] = self.field_data_len - record_start;
+            self.field_ends_len += 1;
+            field_start = field_end + 1;
+        }
+
+        // Consumes the terminator. In case of CRLF, the LF is skipped at the start of the next record.
+        if input[line_end] == b'\n' {
+            self.inner.set_line(self.inner.line() + 1);
+        }
+        Ok((self.terminate_record(arena), &input[line_end + 1..]))
+    }
 }
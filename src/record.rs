@@ -0,0 +1,194 @@
+//! Mapping records to and from Rust structs without Serde.
+//!
+//! `FromRecord` and `ToRecord` are usually derived with the `derive` feature. The
+//! header names of the fields are resolved into column indices once per arena, as a
+//! `ColumnMap`, and each row is then decoded by indexed access to the fields, which are
+//! parsed with `FromStrBytes`. On the write side, the fields are formatted with
+//! `ToStrBytes` straight into the arena.
+//!
+//! The derive macros use the field names as header names, unless renamed with
+//! `#[csv(rename = "name")]`.
+
+use core::fmt;
+use std::error;
+use std::io::Write;
+
+use crate::column::{ColumnErrorKind, FromStrBytes};
+use crate::raw::RawRecord;
+use crate::{ByteRecordArena, ColumnError, Headers};
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RecordError {
+    /// The headers don't have a column for the field.
+    MissingColumn(String),
+    /// 0-based rows and columns, rows not including header.
+    Column(ColumnError),
+}
+
+impl error::Error for RecordError {}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecordError::MissingColumn(name) => write!(f, "missing column '{}'", name),
+            RecordError::Column(err) => err.fmt(f),
+        }
+    }
+}
+
+impl From<ColumnError> for RecordError {
+    fn from(err: ColumnError) -> RecordError {
+        RecordError::Column(err)
+    }
+}
+
+/// The column indices of the fields of a `FromRecord` type.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ColumnMap {
+    indices: Vec<usize>,
+}
+
+impl ColumnMap {
+    /// Looks up the fields by header name. Without headers, the fields are expected
+    /// in the column order.
+    pub fn new(fields: &[&str], headers: Option<&Headers>) -> Result<ColumnMap, RecordError> {
+        let indices = match headers {
+            Some(headers) => fields
+                .iter()
+                .map(|&name| {
+                    headers
+                        .index_of(name)
+                        .ok_or_else(|| RecordError::MissingColumn(name.to_string()))
+                })
+                .collect::<Result<_, _>>()?,
+            None => (0..fields.len()).collect(),
+        };
+        Ok(ColumnMap { indices })
+    }
+
+    /// Returns the column index of the n:th field.
+    pub fn column(&self, field: usize) -> usize {
+        self.indices[field]
+    }
+
+    /// Parses the n:th field from the record. The row of the error is 0.
+    pub fn parse<T: FromStrBytes>(
+        &self,
+        record: RawRecord<'_>,
+        field: usize,
+    ) -> Result<T, ColumnError> {
+        let column = self.column(field);
+        let error = |kind| ColumnError {
+            row: 0,
+            column,
+            kind,
+        };
+        let bytes = record
+            .get(column)
+            .ok_or_else(|| error(ColumnErrorKind::MissingField))?;
+        T::from_str_bytes(bytes).map_err(|err| error(ColumnErrorKind::Parse(err)))
+    }
+}
+
+/// A type that can be decoded from a record.
+pub trait FromRecord: Sized {
+    /// The header names of the fields, in the order of the `ColumnMap`.
+    const FIELDS: &'static [&'static str];
+
+    fn from_record(record: RawRecord<'_>, columns: &ColumnMap) -> Result<Self, ColumnError>;
+}
+
+/// A type that can be written into an arena as a record.
+pub trait ToRecord {
+    /// The header names of the fields, in the column order.
+    const FIELDS: &'static [&'static str];
+
+    fn write_fields(&self, fields: &mut FieldWriter<'_>);
+
+    /// Returns the headers for an arena of records of this type.
+    fn headers() -> Headers {
+        Headers::from_names(Self::FIELDS)
+    }
+}
+
+/// Appends the fields of a record into an arena.
+pub struct FieldWriter<'a> {
+    pub(crate) arena: &'a mut ByteRecordArena,
+}
+
+impl<'a> FieldWriter<'a> {
+    pub fn push<T: ToStrBytes + ?Sized>(&mut self, value: &T) {
+        self.arena
+            .push_field_with(|data| value.write_str_bytes(data));
+    }
+}
+
+/// Formats values as field bytes. The counterpart of `FromStrBytes`.
+pub trait ToStrBytes {
+    fn write_str_bytes(&self, output: &mut Vec<u8>);
+}
+
+macro_rules! impl_to_str_bytes_display {
+    ($($type:ty),*) => {$(
+        impl ToStrBytes for $type {
+            fn write_str_bytes(&self, output: &mut Vec<u8>) {
+                write!(output, "{}", self).expect("writing into a Vec doesn't fail");
+            }
+        }
+    )*}
+}
+
+impl_to_str_bytes_display!(
+    u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64, bool, char
+);
+
+impl ToStrBytes for str {
+    fn write_str_bytes(&self, output: &mut Vec<u8>) {
+        output.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl ToStrBytes for String {
+    fn write_str_bytes(&self, output: &mut Vec<u8>) {
+        output.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl ToStrBytes for [u8] {
+    fn write_str_bytes(&self, output: &mut Vec<u8>) {
+        output.extend_from_slice(self);
+    }
+}
+
+impl ToStrBytes for Vec<u8> {
+    fn write_str_bytes(&self, output: &mut Vec<u8>) {
+        output.extend_from_slice(self);
+    }
+}
+
+/// `None` is written as an empty field.
+impl<T: ToStrBytes> ToStrBytes for Option<T> {
+    fn write_str_bytes(&self, output: &mut Vec<u8>) {
+        if let Some(value) = self {
+            value.write_str_bytes(output);
+        }
+    }
+}
+
+impl<T: ToStrBytes + ?Sized> ToStrBytes for &T {
+    fn write_str_bytes(&self, output: &mut Vec<u8>) {
+        (**self).write_str_bytes(output);
+    }
+}
+
+pub(crate) fn decode_records<T: FromRecord>(
+    arena: &ByteRecordArena,
+) -> Result<Vec<T>, RecordError> {
+    let columns = ColumnMap::new(T::FIELDS, arena.headers())?;
+    let mut output = Vec::with_capacity(arena.record_count() as usize);
+    for (row, record) in arena.iter().enumerate() {
+        let value = T::from_record(record, &columns).map_err(|err| ColumnError { row, ..err })?;
+        output.push(value);
+    }
+    Ok(output)
+}
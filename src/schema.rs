@@ -0,0 +1,420 @@
+//! Schema inference over `ByteRecordArena`s.
+//!
+//! `SchemaInferrer` scans one or more arenas and collects per-column statistics:
+//! the narrowest type that fits every non-empty field, nullability, min/max values,
+//! an estimate of the distinct value count and the maximum field width.
+//! The type guessing follows the same order as the `deserialize_any` support
+//! of the Serde deserializer: bool, u64, i64, u128, i128, f64, str and finally bytes.
+
+use core::str;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{BoolFormat, BoolFormats, ByteRecordArena, NullTokens};
+
+/// The inferred type of a column.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ColumnType {
+    /// The column contains no non-empty fields, so there's nothing to infer from.
+    Empty,
+    Bool,
+    UInt64,
+    Int64,
+    UInt128,
+    Int128,
+    Float64,
+    Str,
+    Bytes,
+}
+
+/// A minimum or a maximum value of a column.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Bound {
+    Bool(bool),
+    UInt(u128),
+    Int(i128),
+    Float(f64),
+    Str(String),
+}
+
+/// The inferred schema of a single column.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ColumnSchema {
+    /// The header name of the column, if the scanned arenas had headers.
+    pub name: Option<String>,
+    pub column_type: ColumnType,
+    /// The boolean format the column was inferred with.
+    /// `ByteRecordArena::rows_as_values_with` parses `Bool` columns with it.
+    pub bool_format: BoolFormat,
+    /// True if the column contains empty fields.
+    pub nullable: bool,
+    pub min: Option<Bound>,
+    pub max: Option<Bound>,
+    /// An estimate of the count of distinct non-empty values.
+    pub distinct_estimate: u64,
+    /// The maximum field width in bytes.
+    pub max_width: usize,
+}
+
+/// The inferred schema of the scanned records.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Schema {
+    pub columns: Vec<ColumnSchema>,
+    /// The count of scanned records.
+    pub record_count: u64,
+}
+
+pub struct SchemaInferrer {
+    nulls: NullTokens,
+    bools: BoolFormats,
+    names: Option<Vec<String>>,
+    columns: Vec<ColumnStats>,
+    record_count: u64,
+}
+
+impl Default for SchemaInferrer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SchemaInferrer {
+    pub fn new() -> SchemaInferrer {
+        SchemaInferrer {
+            nulls: NullTokens::default(),
+            bools: BoolFormats::default(),
+            names: None,
+            columns: Vec::new(),
+            record_count: 0,
+        }
+    }
+
+    /// Sets the null tokens. Fields matching them are treated as empty fields:
+    /// they make the column nullable and don't affect the type.
+    pub fn set_null_tokens(&mut self, nulls: NullTokens) {
+        self.nulls = nulls;
+    }
+
+    /// Sets the boolean formats. A column is inferred as `Bool` if all its non-empty fields
+    /// are values of the format of the column.
+    pub fn set_bool_formats(&mut self, bools: BoolFormats) {
+        self.bools = bools;
+    }
+
+    /// Scans the full records of an arena. May be called repeatedly for consecutive arenas.
+    /// The column names are taken from the headers of the first arena that has them.
+    pub fn scan(&mut self, arena: &ByteRecordArena) {
+        if self.names.is_none() {
+            if let Some(headers) = arena.headers() {
+                self.names = Some(
+                    headers
+                        .iter()
+                        .map(|name| String::from_utf8_lossy(name).into_owned())
+                        .collect(),
+                );
+            }
+        }
+        let bools = &self.bools;
+        let bool_formats: Vec<&BoolFormat> = match &self.names {
+            Some(names) => names
+                .iter()
+                .map(|name| bools.format(Some(name.as_bytes())))
+                .collect(),
+            None => Vec::new(),
+        };
+        for record in arena.iter() {
+            if record.field_count() > self.columns.len() {
+                self.columns
+                    .resize_with(record.field_count(), ColumnStats::new);
+            }
+            for (i, (field, column)) in record.iter().zip(self.columns.iter_mut()).enumerate() {
+                let bool_format = bool_formats.get(i).copied().unwrap_or(&bools.default);
+                column.update(self.nulls.normalize(field), bool_format);
+            }
+            self.record_count += 1;
+        }
+    }
+
+    /// Returns the schema inferred from the records scanned so far.
+    pub fn schema(&self) -> Schema {
+        let name_count = self.names.as_ref().map_or(0, |names| names.len());
+        let columns = (0..self.columns.len().max(name_count))
+            .map(|i| {
+                let name = self.names.as_ref().and_then(|names| names.get(i).cloned());
+                let bool_format = self.bools.format(name.as_ref().map(|name| name.as_bytes()));
+                match self.columns.get(i) {
+                    Some(column) => column.schema(name, bool_format),
+                    None => ColumnStats::new().schema(name, bool_format),
+                }
+            })
+            .collect();
+        Schema {
+            columns,
+            record_count: self.record_count,
+        }
+    }
+}
+
+struct ColumnStats {
+    nullable: bool,
+    seen: bool,
+    is_bool: bool,
+    is_u64: bool,
+    is_i64: bool,
+    is_u128: bool,
+    is_i128: bool,
+    is_f64: bool,
+    is_str: bool,
+    bool_range: Option<(bool, bool)>,
+    u128_range: Option<(u128, u128)>,
+    i128_range: Option<(i128, i128)>,
+    f64_range: Option<(f64, f64)>,
+    bytes_range: Option<(Vec<u8>, Vec<u8>)>,
+    distinct: HyperLogLog,
+    max_width: usize,
+}
+
+fn update_range<T: PartialOrd + Copy>(range: &mut Option<(T, T)>, value: T) {
+    match range {
+        Some((min, max)) => {
+            if value < *min {
+                *min = value;
+            }
+            if value > *max {
+                *max = value;
+            }
+        }
+        None => *range = Some((value, value)),
+    }
+}
+
+impl ColumnStats {
+    fn new() -> ColumnStats {
+        ColumnStats {
+            nullable: false,
+            seen: false,
+            is_bool: true,
+            is_u64: true,
+            is_i64: true,
+            is_u128: true,
+            is_i128: true,
+            is_f64: true,
+            is_str: true,
+            bool_range: None,
+            u128_range: None,
+            i128_range: None,
+            f64_range: None,
+            bytes_range: None,
+            distinct: HyperLogLog::new(),
+            max_width: 0,
+        }
+    }
+
+    fn update(&mut self, field: &[u8], bool_format: &BoolFormat) {
+        self.max_width = self.max_width.max(field.len());
+        if field.is_empty() {
+            self.nullable = true;
+            return;
+        }
+        self.seen = true;
+        self.distinct.insert(field);
+
+        match &mut self.bytes_range {
+            Some((min, max)) => {
+                if field < min.as_slice() {
+                    *min = field.to_vec();
+                } else if field > max.as_slice() {
+                    *max = field.to_vec();
+                }
+            }
+            None => self.bytes_range = Some((field.to_vec(), field.to_vec())),
+        }
+
+        let s = match str::from_utf8(field) {
+            Ok(s) => s,
+            Err(_) => {
+                self.is_bool = false;
+                self.is_u64 = false;
+                self.is_i64 = false;
+                self.is_u128 = false;
+                self.is_i128 = false;
+                self.is_f64 = false;
+                self.is_str = false;
+                return;
+            }
+        };
+
+        if self.is_bool {
+            match bool_format.parse(field) {
+                Some(b) => update_range(&mut self.bool_range, b),
+                None => self.is_bool = false,
+            }
+        }
+        if self.is_u64 || self.is_u128 {
+            match s.parse::<u128>() {
+                Ok(n) => {
+                    self.is_u64 = self.is_u64 && n <= u128::from(u64::MAX);
+                    update_range(&mut self.u128_range, n);
+                }
+                Err(_) => {
+                    self.is_u64 = false;
+                    self.is_u128 = false;
+                }
+            }
+        }
+        if self.is_i64 || self.is_i128 {
+            match s.parse::<i128>() {
+                Ok(n) => {
+                    self.is_i64 = self.is_i64 && i64::MIN as i128 <= n && n <= i64::MAX as i128;
+                    update_range(&mut self.i128_range, n);
+                }
+                Err(_) => {
+                    self.is_i64 = false;
+                    self.is_i128 = false;
+                }
+            }
+        }
+        if self.is_f64 {
+            match s.parse::<f64>() {
+                Ok(n) => update_range(&mut self.f64_range, n),
+                Err(_) => self.is_f64 = false,
+            }
+        }
+    }
+
+    fn column_type(&self) -> ColumnType {
+        if !self.seen {
+            ColumnType::Empty
+        } else if self.is_bool {
+            ColumnType::Bool
+        } else if self.is_u64 {
+            ColumnType::UInt64
+        } else if self.is_i64 {
+            ColumnType::Int64
+        } else if self.is_u128 {
+            ColumnType::UInt128
+        } else if self.is_i128 {
+            ColumnType::Int128
+        } else if self.is_f64 {
+            ColumnType::Float64
+        } else if self.is_str {
+            ColumnType::Str
+        } else {
+            ColumnType::Bytes
+        }
+    }
+
+    fn schema(&self, name: Option<String>, bool_format: &BoolFormat) -> ColumnSchema {
+        let column_type = self.column_type();
+        let (min, max) = match column_type {
+            ColumnType::Empty | ColumnType::Bytes => (None, None),
+            ColumnType::Bool => bounds(self.bool_range, Bound::Bool),
+            ColumnType::UInt64 | ColumnType::UInt128 => bounds(self.u128_range, Bound::UInt),
+            ColumnType::Int64 | ColumnType::Int128 => bounds(self.i128_range, Bound::Int),
+            ColumnType::Float64 => bounds(self.f64_range, Bound::Float),
+            ColumnType::Str => match &self.bytes_range {
+                Some((min, max)) => (
+                    Some(Bound::Str(String::from_utf8_lossy(min).into_owned())),
+                    Some(Bound::Str(String::from_utf8_lossy(max).into_owned())),
+                ),
+                None => (None, None),
+            },
+        };
+        ColumnSchema {
+            name,
+            column_type,
+            bool_format: bool_format.clone(),
+            nullable: self.nullable || !self.seen,
+            min,
+            max,
+            distinct_estimate: self.distinct.estimate(),
+            max_width: self.max_width,
+        }
+    }
+}
+
+fn bounds<T>(range: Option<(T, T)>, bound: impl Fn(T) -> Bound) -> (Option<Bound>, Option<Bound>) {
+    match range {
+        Some((min, max)) => (Some(bound(min)), Some(bound(max))),
+        None => (None, None),
+    }
+}
+
+const HLL_BITS: u32 = 10;
+const HLL_REGISTERS: usize = 1 << HLL_BITS;
+
+/// A HyperLogLog distinct count estimator with 1024 registers (about 3% standard error).
+struct HyperLogLog {
+    registers: Box<[u8; HLL_REGISTERS]>,
+}
+
+impl HyperLogLog {
+    fn new() -> HyperLogLog {
+        HyperLogLog {
+            registers: Box::new([0; HLL_REGISTERS]),
+        }
+    }
+
+    fn insert(&mut self, data: &[u8]) {
+        let hash = hash64(data);
+        let index = (hash >> (64 - HLL_BITS)) as usize;
+        let rank = ((hash << HLL_BITS) | (1 << (HLL_BITS - 1))).leading_zeros() + 1;
+        let register = &mut self.registers[index];
+        *register = (*register).max(rank as u8);
+    }
+
+    fn estimate(&self) -> u64 {
+        let m = HLL_REGISTERS as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self
+            .registers
+            .iter()
+            .map(|&r| 1.0 / f64::from(1u32 << r))
+            .sum();
+        let raw = alpha * m * m / sum;
+        let zeros = self.registers.iter().filter(|&&r| r == 0).count();
+        let estimate = if raw <= 2.5 * m && zeros > 0 {
+            // Linear counting is more accurate for small cardinalities
+            m * (m / zeros as f64).ln()
+        } else {
+            raw
+        };
+        estimate.round() as u64
+    }
+}
+
+/// FNV-1a followed by the MurmurHash3 finalizer to spread the bits.
+fn hash64(data: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &byte in data {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    hash ^ (hash >> 33)
+}
+
+#[test]
+fn test_hyperloglog() {
+    let mut hll = HyperLogLog::new();
+    for i in 0..10_000u32 {
+        hll.insert(i.to_string().as_bytes());
+        hll.insert(i.to_string().as_bytes());
+    }
+    let estimate = hll.estimate() as f64;
+    assert!((estimate - 10_000.0).abs() < 1_000.0, "{}", estimate);
+
+    let mut hll = HyperLogLog::new();
+    for s in &["a", "b", "c", "a"] {
+        hll.insert(s.as_bytes());
+    }
+    assert_eq!(hll.estimate(), 3);
+}
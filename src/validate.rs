@@ -0,0 +1,251 @@
+//! Declarative per-column validation, for rejecting bad input before deserializing it.
+//!
+//! The rules are attached to columns by header name. Apart from `Rule::Required`, the
+//! rules skip empty fields, so a column may be optional but still constrained when
+//! present. Uniqueness is checked within the validated arena.
+
+use core::fmt;
+use std::collections::HashMap;
+
+use crate::column::FromStrBytes;
+use crate::pattern::Pattern;
+use crate::ByteRecordArena;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Rule {
+    /// The field must not be empty.
+    Required,
+    /// The field must be at most this many characters long,
+    /// or bytes if it isn't valid UTF-8.
+    MaxLength(usize),
+    /// The whole field must match the pattern.
+    Pattern(Pattern),
+    /// The field must be a number within the inclusive bounds.
+    Range { min: Option<f64>, max: Option<f64> },
+    /// The field must be one of the values.
+    OneOf(Vec<Vec<u8>>),
+    /// The field must not occur on another row.
+    Unique,
+}
+
+impl Rule {
+    /// A `Rule::OneOf` with the given values.
+    pub fn one_of<I>(values: I) -> Rule
+    where
+        I: IntoIterator,
+        I::Item: AsRef<[u8]>,
+    {
+        Rule::OneOf(
+            values
+                .into_iter()
+                .map(|value| value.as_ref().to_vec())
+                .collect(),
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ViolationKind {
+    Empty,
+    TooLong {
+        length: usize,
+        max: usize,
+    },
+    NoMatch(Pattern),
+    NotANumber,
+    OutOfRange {
+        min: Option<f64>,
+        max: Option<f64>,
+    },
+    NotAllowed,
+    /// 0-based row of the first occurrence.
+    Duplicate {
+        first_row: usize,
+    },
+}
+
+impl fmt::Display for ViolationKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ViolationKind::Empty => write!(f, "value is required"),
+            ViolationKind::TooLong { length, max } => {
+                write!(f, "length {} exceeds the maximum of {}", length, max)
+            }
+            ViolationKind::NoMatch(pattern) => write!(f, "doesn't match '{}'", pattern),
+            ViolationKind::NotANumber => write!(f, "not a number"),
+            ViolationKind::OutOfRange { min, max } => match (min, max) {
+                (Some(min), Some(max)) => write!(f, "not between {} and {}", min, max),
+                (Some(min), None) => write!(f, "less than {}", min),
+                (None, Some(max)) => write!(f, "greater than {}", max),
+                (None, None) => write!(f, "NaN is out of range"),
+            },
+            ViolationKind::NotAllowed => write!(f, "not one of the allowed values"),
+            ViolationKind::Duplicate { first_row } => {
+                write!(f, "duplicate of row {}", first_row)
+            }
+        }
+    }
+}
+
+/// A field that breaks a rule.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Violation {
+    /// 0-based row in the arena, header not in count.
+    pub row: usize,
+    /// The 0-based record number in the input, counted from the start `Position` of the arena,
+    /// if it has one. The arena doesn't keep the positions of the other records, so the byte
+    /// offset and the line aren't known; records with quoted line breaks span several lines.
+    pub record_num: Option<u64>,
+    pub column: String,
+    pub value: Vec<u8>,
+    pub kind: ViolationKind,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "row {}", self.row)?;
+        if let Some(record_num) = self.record_num {
+            write!(f, " (record {})", record_num)?;
+        }
+        write!(
+            f,
+            ", column '{}', value '{}': {}",
+            self.column,
+            String::from_utf8_lossy(&self.value),
+            self.kind
+        )
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ValidationReport {
+    /// The columns that have rules but aren't in the headers.
+    pub missing_columns: Vec<String>,
+    /// The violations in row order.
+    pub violations: Vec<Violation>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.missing_columns.is_empty() && self.violations.is_empty()
+    }
+}
+
+/// One line per missing column and per violation.
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for column in &self.missing_columns {
+            writeln!(f, "missing column '{}'", column)?;
+        }
+        for violation in &self.violations {
+            writeln!(f, "{}", violation)?;
+        }
+        Ok(())
+    }
+}
+
+/// Validation rules by header name.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Validator {
+    columns: Vec<(Vec<u8>, Vec<Rule>)>,
+}
+
+impl Validator {
+    pub fn new() -> Validator {
+        Validator::default()
+    }
+
+    /// Adds a rule for the column with the given header name.
+    pub fn add_rule<N: AsRef<[u8]>>(&mut self, name: N, rule: Rule) {
+        let name = name.as_ref();
+        match self.columns.iter_mut().find(|(column, _)| column == name) {
+            Some((_, rules)) => rules.push(rule),
+            None => self.columns.push((name.to_vec(), vec![rule])),
+        }
+    }
+
+    /// Checks the full records of the arena. Without headers, all columns are missing.
+    pub fn validate(&self, arena: &ByteRecordArena) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        let mut columns = Vec::with_capacity(self.columns.len());
+        for (name, rules) in &self.columns {
+            match arena.headers().and_then(|headers| headers.index_of(name)) {
+                Some(index) => columns.push((index, String::from_utf8_lossy(name), rules)),
+                None => report
+                    .missing_columns
+                    .push(String::from_utf8_lossy(name).into_owned()),
+            }
+        }
+        // The first rows of the values of the columns with `Rule::Unique`
+        let mut seen: Vec<HashMap<&[u8], usize>> = vec![HashMap::new(); columns.len()];
+        let start = arena.start_pos().map(|pos| pos.record);
+
+        for (row, record) in arena.iter().enumerate() {
+            for (&(index, ref name, rules), seen) in columns.iter().zip(&mut seen) {
+                let field = record.get(index).unwrap_or_default();
+                for rule in rules {
+                    if let Some(kind) = check(rule, field, row, seen) {
+                        report.violations.push(Violation {
+                            row,
+                            record_num: start.map(|start| start + row as u64),
+                            column: name.to_string(),
+                            value: field.to_vec(),
+                            kind,
+                        });
+                    }
+                }
+            }
+        }
+        report
+    }
+}
+
+fn check<'a>(
+    rule: &Rule,
+    field: &'a [u8],
+    row: usize,
+    seen: &mut HashMap<&'a [u8], usize>,
+) -> Option<ViolationKind> {
+    if field.is_empty() {
+        return match rule {
+            Rule::Required => Some(ViolationKind::Empty),
+            _ => None,
+        };
+    }
+    match rule {
+        Rule::Required => None,
+        Rule::MaxLength(max) => {
+            let length = match core::str::from_utf8(field) {
+                Ok(s) => s.chars().count(),
+                Err(_) => field.len(),
+            };
+            (length > *max).then_some(ViolationKind::TooLong { length, max: *max })
+        }
+        Rule::Pattern(pattern) => {
+            (!pattern.is_match(field)).then(|| ViolationKind::NoMatch(pattern.clone()))
+        }
+        Rule::Range { min, max } => match f64::from_str_bytes(field) {
+            Ok(n) => {
+                let in_range = !n.is_nan()
+                    && min.map_or(true, |min| n >= min)
+                    && max.map_or(true, |max| n <= max);
+                (!in_range).then_some(ViolationKind::OutOfRange {
+                    min: *min,
+                    max: *max,
+                })
+            }
+            Err(_) => Some(ViolationKind::NotANumber),
+        },
+        Rule::OneOf(values) => {
+            (!values.iter().any(|value| value == field)).then_some(ViolationKind::NotAllowed)
+        }
+        Rule::Unique => match seen.get(field) {
+            Some(&first_row) if first_row != row => Some(ViolationKind::Duplicate { first_row }),
+            Some(_) => None,
+            None => {
+                seen.insert(field, row);
+                None
+            }
+        },
+    }
+}
@@ -0,0 +1,234 @@
+//! Untyped field values, for handling records whose schema isn't known at compile time.
+//!
+//! The types are guessed in the same order as by `deserialize_any` of the Serde
+//! deserializer: bool, u64, i64, f64, str and finally bytes. Integers that don't fit
+//! in 64 bits are kept as strings. A `Schema` can be given to parse the columns as
+//! the inferred types instead of guessing each field separately.
+
+use alloc::collections::BTreeMap;
+use core::str;
+
+use crate::raw::RawRecordsIter;
+use crate::schema::{ColumnType, Schema};
+use crate::{BoolFormat, Headers};
+
+/// An untyped field value, borrowing from the arena.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value<'a> {
+    /// An empty field.
+    Null,
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    Str(&'a str),
+    /// A field that isn't valid UTF-8.
+    Bytes(&'a [u8]),
+}
+
+pub(crate) fn try_positive_integer64_bytes(s: &[u8]) -> Option<u64> {
+    str::from_utf8(s).ok().and_then(|s| s.parse().ok())
+}
+
+pub(crate) fn try_negative_integer64_bytes(s: &[u8]) -> Option<i64> {
+    str::from_utf8(s).ok().and_then(|s| s.parse().ok())
+}
+
+/// Tells if the number is an integer, whether or not it fits in 64 bits.
+fn is_integer(number: &[u8]) -> bool {
+    let digits = match number.first() {
+        Some(b'+') | Some(b'-') => &number[1..],
+        _ => number,
+    };
+    !digits.is_empty() && digits.iter().all(u8::is_ascii_digit)
+}
+
+pub(crate) fn try_float_bytes(s: &[u8]) -> Option<f64> {
+    str::from_utf8(s).ok().and_then(|s| s.parse().ok())
+}
+
+/// Guesses the type of the field. `number` is the field converted into the Rust number syntax.
+pub(crate) fn infer<'a>(field: &'a [u8], number: &[u8], bool_format: &BoolFormat) -> Value<'a> {
+    if field.is_empty() {
+        Value::Null
+    } else if let Some(b) = bool_format.parse(field) {
+        Value::Bool(b)
+    } else if let Some(n) = try_positive_integer64_bytes(number) {
+        Value::UInt(n)
+    } else if let Some(n) = try_negative_integer64_bytes(number) {
+        Value::Int(n)
+    } else if is_integer(number) {
+        // Rather than a float that loses precision
+        str_or_bytes(field)
+    } else if let Some(n) = try_float_bytes(number) {
+        Value::Float(n)
+    } else {
+        str_or_bytes(field)
+    }
+}
+
+fn str_or_bytes(field: &[u8]) -> Value<'_> {
+    match str::from_utf8(field) {
+        Ok(s) => Value::Str(s),
+        Err(_) => Value::Bytes(field),
+    }
+}
+
+impl<'a> Value<'a> {
+    /// Guesses the type of the field.
+    pub fn infer(field: &'a [u8]) -> Value<'a> {
+        infer(field, field, &BoolFormat::default())
+    }
+
+    /// Parses the field as the given type, or guesses the type if the field doesn't fit it.
+    pub fn with_type(field: &'a [u8], column_type: ColumnType) -> Value<'a> {
+        Value::with_type_and_format(field, column_type, &BoolFormat::default())
+    }
+
+    /// Like `with_type`, but with the given boolean format instead of the default one.
+    pub fn with_type_and_format(
+        field: &'a [u8],
+        column_type: ColumnType,
+        bool_format: &BoolFormat,
+    ) -> Value<'a> {
+        if field.is_empty() {
+            return Value::Null;
+        }
+        let value = match column_type {
+            ColumnType::Bool => bool_format.parse(field).map(Value::Bool),
+            ColumnType::UInt64 => try_positive_integer64_bytes(field).map(Value::UInt),
+            ColumnType::Int64 => try_negative_integer64_bytes(field).map(Value::Int),
+            ColumnType::Float64 => try_float_bytes(field).map(Value::Float),
+            ColumnType::UInt128 | ColumnType::Int128 | ColumnType::Str => Some(str_or_bytes(field)),
+            ColumnType::Bytes => Some(Value::Bytes(field)),
+            ColumnType::Empty => None,
+        };
+        value.unwrap_or_else(|| infer(field, field, bool_format))
+    }
+
+    pub fn is_null(&self) -> bool {
+        *self == Value::Null
+    }
+}
+
+/// An iterator over the full records of an arena as rows of values,
+/// returned by `ByteRecordArena::rows_as_values`.
+pub struct ValueRowsIter<'a> {
+    pub(crate) records: RawRecordsIter<'a>,
+    pub(crate) types: Option<Vec<(ColumnType, BoolFormat)>>,
+}
+
+impl<'a> ValueRowsIter<'a> {
+    pub(crate) fn new(records: RawRecordsIter<'a>, schema: Option<&Schema>) -> ValueRowsIter<'a> {
+        ValueRowsIter {
+            records,
+            types: schema.map(|schema| {
+                schema
+                    .columns
+                    .iter()
+                    .map(|column| (column.column_type, column.bool_format.clone()))
+                    .collect()
+            }),
+        }
+    }
+}
+
+impl<'a> Iterator for ValueRowsIter<'a> {
+    type Item = Vec<Value<'a>>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let record = self.records.next()?;
+        let types = self.types.as_deref().unwrap_or(&[]);
+        Some(
+            record
+                .iter()
+                .enumerate()
+                .map(|(i, field)| match types.get(i) {
+                    Some((column_type, bool_format)) => {
+                        Value::with_type_and_format(field, *column_type, bool_format)
+                    }
+                    None => Value::infer(field),
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Maps the values of a row by the header names. If a name occurs more than once,
+/// the first column wins. Fails if a name isn't valid UTF-8.
+pub fn row_to_map<'a>(
+    headers: &'a Headers,
+    row: &[Value<'a>],
+) -> Result<BTreeMap<&'a str, Value<'a>>, str::Utf8Error> {
+    let mut map = BTreeMap::new();
+    for (name, value) in headers.iter().zip(row) {
+        map.entry(str::from_utf8(name)?).or_insert(*value);
+    }
+    Ok(map)
+}
+
+#[cfg(feature = "serde_json")]
+impl<'a> From<Value<'a>> for serde_json::Value {
+    /// Non-finite floats become nulls and bytes are converted into strings lossily.
+    fn from(value: Value<'a>) -> serde_json::Value {
+        match value {
+            Value::Null => serde_json::Value::Null,
+            Value::Bool(b) => b.into(),
+            Value::Int(n) => n.into(),
+            Value::UInt(n) => n.into(),
+            Value::Float(n) => serde_json::Number::from_f64(n)
+                .map_or(serde_json::Value::Null, serde_json::Value::Number),
+            Value::Str(s) => s.into(),
+            Value::Bytes(b) => String::from_utf8_lossy(b).into(),
+        }
+    }
+}
+
+/// Converts a row into a JSON object keyed by the header names, or into an array
+/// if there are no headers. Header names are converted into strings lossily.
+#[cfg(feature = "serde_json")]
+pub fn row_to_json(headers: Option<&Headers>, row: &[Value<'_>]) -> serde_json::Value {
+    match headers {
+        Some(headers) => {
+            let mut object = serde_json::Map::new();
+            for (name, value) in headers.iter().zip(row) {
+                let name = String::from_utf8_lossy(name).into_owned();
+                object.entry(name).or_insert_with(|| (*value).into());
+            }
+            serde_json::Value::Object(object)
+        }
+        None => row
+            .iter()
+            .map(|&value| serde_json::Value::from(value))
+            .collect(),
+    }
+}
+
+#[test]
+fn test_infer() {
+    assert_eq!(Value::infer(b""), Value::Null);
+    assert_eq!(Value::infer(b"true"), Value::Bool(true));
+    assert_eq!(Value::infer(b"12"), Value::UInt(12));
+    assert_eq!(Value::infer(b"-12"), Value::Int(-12));
+    assert_eq!(Value::infer(b"1.5"), Value::Float(1.5));
+    assert_eq!(Value::infer(b"abc"), Value::Str("abc"));
+    assert_eq!(Value::infer(b"\xff"), Value::Bytes(b"\xff"));
+    assert_eq!(
+        Value::infer(b"123456789012345678901234567890"),
+        Value::Str("123456789012345678901234567890")
+    );
+    assert_eq!(
+        Value::infer(b"-18446744073709551616"),
+        Value::Str("-18446744073709551616")
+    );
+    assert_eq!(Value::infer(b"1e30"), Value::Float(1e30));
+    assert_eq!(
+        Value::with_type(b"18446744073709551616", ColumnType::UInt64),
+        Value::Str("18446744073709551616")
+    );
+    assert_eq!(
+        Value::with_type(b"12", ColumnType::Float64),
+        Value::Float(12.0)
+    );
+    assert_eq!(Value::with_type(b"007", ColumnType::Str), Value::Str("007"));
+    assert_eq!(Value::with_type(b"x", ColumnType::UInt64), Value::Str("x"));
+}
@@ -6,6 +6,8 @@ use crate::ByteRecordArena;
 pub struct Writer {
     inner: csv_core::Writer,
     skip_header: bool,
+    normalized_headers: bool,
+    null_value: Option<Vec<u8>>,
     bytes_written: u64,
     records_written: u64,
 }
@@ -15,6 +17,8 @@ impl Writer {
         Self {
             inner: csv_core::WriterBuilder::new().delimiter(delim).build(),
             skip_header,
+            normalized_headers: false,
+            null_value: None,
             bytes_written: 0,
             records_written: 0,
         }
@@ -24,11 +28,25 @@ impl Writer {
         Self {
             inner: writer,
             skip_header: first_row_is_header,
+            normalized_headers: false,
+            null_value: None,
             bytes_written: 0,
             records_written: 0,
         }
     }
 
+    /// By default, the header rows are written as they were read from the input.
+    /// If set, the header names normalized by the `HeaderPolicy` are written instead.
+    pub fn set_normalized_headers(&mut self, normalized_headers: bool) {
+        self.normalized_headers = normalized_headers;
+    }
+
+    /// Sets the representation of missing values, e.g. `NULL` or `\N`.
+    /// Empty fields are written as the given value instead.
+    pub fn set_null_value(&mut self, null_value: Option<Vec<u8>>) {
+        self.null_value = null_value;
+    }
+
     pub fn records_written(&self) -> u64 {
         self.records_written
     }
@@ -37,10 +55,15 @@ impl Writer {
         record: &RawRecord,
         writer: &mut csv_core::Writer,
         out_buffer: &mut [u8],
+        null_value: Option<&[u8]>,
     ) -> usize {
         let mut record_bytes_out = 0;
         let field_count = record.field_count() - 1;
         for (i, field) in record.iter().enumerate() {
+            let field = match null_value {
+                Some(null_value) if field.is_empty() => null_value,
+                _ => field,
+            };
             let output = &mut out_buffer[record_bytes_out..];
             let (res, bytes_in, bytes_out) = writer.field(field, output);
             // We expect the output buffer to be prepared to have enough space
@@ -64,46 +87,62 @@ impl Writer {
         record_bytes_out
     }
 
-
     pub fn dump_arena(&mut self, out_buffer: &mut Vec<u8>, arena_outer: &ByteRecordArena) {
         let arena = &arena_outer.inner;
 
+        let header_rows = match &arena_outer.headers_inner {
+            Some(headers) if self.skip_header.not() => {
+                self.skip_header = true;
+                if self.normalized_headers {
+                    vec![RawRecord {
+                        field_data: headers.name_data.as_slice(),
+                        field_ends: headers.name_ends.as_slice(),
+                    }]
+                } else {
+                    headers.raw_rows()
+                }
+            }
+            _ => Vec::new(),
+        };
+
         // considering CSV quoting, output size is 2 + (2 * field.len()) at maximum
+        let header_len: usize = header_rows
+            .iter()
+            .map(|row| 2 * row.field_data.len() + 3 * row.field_count() + 1)
+            .sum();
         let fields_len = 2 + (2 * arena.field_data.len());
         let separators_len = arena.field_ends.len();
         let terminators_len = arena.record_ends.len();
-        let max_output_len = fields_len + separators_len + terminators_len;
+        let null_len = match &self.null_value {
+            Some(null_value) => 2 * null_value.len() * arena.field_ends.len(),
+            None => 0,
+        };
+        let max_output_len = header_len + fields_len + separators_len + terminators_len + null_len;
         out_buffer.clear();
-        out_buffer.reserve(max_output_len);
-        // This unsafe is okay, because
-        // 1) the vec only has allocated memory, guaranteed by `reserve`
-        // 2) We don't attempt to read the contents (that might be indeterminate bytes), only write.
-        // 3) We set the length of the vector back to area what is certainly written into in the end.
-        unsafe { out_buffer.set_len(max_output_len) };
+        // The records are written into the slice, so it's zero-filled up to the maximum size
+        // and truncated to the written length in the end.
+        out_buffer.resize(max_output_len, 0);
         let mut total_bytes_out = 0;
 
-        if let Some(headers) = &arena_outer.headers_inner {
-            if self.skip_header.not() {
-                let header_record = RawRecord {
-                    field_data: headers.name_data.as_slice(),
-                    field_ends: headers.name_ends.as_slice(),
-                };
-                total_bytes_out += Self::write_record(
-                    &header_record,
-                    &mut self.inner,
-                    &mut out_buffer[total_bytes_out..],
-                );
-                self.skip_header = true;
-            }
+        for header_record in &header_rows {
+            total_bytes_out += Self::write_record(
+                header_record,
+                &mut self.inner,
+                &mut out_buffer[total_bytes_out..],
+                None,
+            );
         }
 
         for record in arena.iter() {
-            total_bytes_out +=
-                Self::write_record(&record, &mut self.inner, &mut out_buffer[total_bytes_out..]);
+            total_bytes_out += Self::write_record(
+                &record,
+                &mut self.inner,
+                &mut out_buffer[total_bytes_out..],
+                self.null_value.as_deref(),
+            );
         }
         self.bytes_written += out_buffer.len() as u64;
         self.records_written += arena_outer.record_count();
-        // out_buffer is "safe" again now:
         out_buffer.truncate(total_bytes_out);
     }
 }
@@ -96,3 +96,1667 @@ fn test_count() {
     assert_eq!(arena_a.record_count(), 2); // Header doesn't count
     assert_eq!(arena_b.record_count(), 3);
 }
+
+#[cfg(feature = "arrow")]
+#[test]
+fn test_arrow_record_batch() {
+    use arrow_array::{Array, BooleanArray, Date32Array, Float64Array, Int64Array, StringArray};
+    use arrow_schema::DataType;
+
+    let chunk = r#""ID","PRICE","ACTIVE","DAY","NAME"
+1,77.00,true,2013/05/12,"QU"
+2,3.50,false,2013/05/13,
+3,,true,2013/05/14,"漢"
+"#;
+
+    let mut arena = ByteRecordArena::new();
+    let mut reader = Reader::new(true, b',');
+    reader.fill_arena(chunk.as_bytes(), &mut arena).unwrap();
+
+    let batch = arena.to_record_batch().unwrap();
+    let schema = batch.schema();
+    let types: Vec<_> = schema
+        .fields()
+        .iter()
+        .map(|f| f.data_type().clone())
+        .collect();
+    assert_eq!(
+        types,
+        [
+            DataType::Int64,
+            DataType::Float64,
+            DataType::Boolean,
+            DataType::Date32,
+            DataType::Utf8
+        ]
+    );
+    assert_eq!(schema.field(0).name(), "ID");
+    assert!(!schema.field(0).is_nullable());
+    assert!(schema.field(1).is_nullable());
+
+    let col = |i| batch.column(i).as_any();
+    let ids = col(0).downcast_ref::<Int64Array>().unwrap();
+    assert_eq!(ids.values(), &[1, 2, 3]);
+    let prices = col(1).downcast_ref::<Float64Array>().unwrap();
+    assert_eq!(prices.value(1), 3.5);
+    assert!(prices.is_null(2));
+    let active = col(2).downcast_ref::<BooleanArray>().unwrap();
+    assert!(!active.value(1));
+    let days = col(3).downcast_ref::<Date32Array>().unwrap();
+    assert_eq!(days.value(0), 15837);
+    let names = col(4).downcast_ref::<StringArray>().unwrap();
+    assert_eq!(names.value(2), "漢");
+    assert!(names.is_null(1));
+}
+
+#[test]
+fn test_schema_inference() {
+    use sleek_csv::schema::{Bound, ColumnType};
+    use sleek_csv::SchemaInferrer;
+
+    let chunk_a = r#""COL_1","COL_2","COL_3","COL_4","COL_5"
+"QU","2012060000",77.00,true,-1
+"QU","2012060000",3.00,false,
+"#;
+    let chunk_b = r#""QX","2013060000",7.00,true,340282366920938463463374607431768211455
+"#;
+
+    let mut arena_a = ByteRecordArena::new();
+    let mut arena_b = ByteRecordArena::new();
+    let mut reader = Reader::new(true, b',');
+    reader.fill_arena(chunk_a.as_bytes(), &mut arena_a).unwrap();
+    arena_a.migrate_partial(&mut arena_b);
+    reader.fill_arena(chunk_b.as_bytes(), &mut arena_b).unwrap();
+
+    let mut inferrer = SchemaInferrer::new();
+    inferrer.scan(&arena_a);
+    inferrer.scan(&arena_b);
+    let schema = inferrer.schema();
+
+    assert_eq!(schema.record_count, 3);
+    let types: Vec<_> = schema.columns.iter().map(|c| c.column_type).collect();
+    assert_eq!(
+        types,
+        [
+            ColumnType::Str,
+            ColumnType::UInt64,
+            ColumnType::Float64,
+            ColumnType::Bool,
+            ColumnType::Float64,
+        ]
+    );
+    let col = &schema.columns[0];
+    assert_eq!(col.name.as_deref(), Some("COL_1"));
+    assert_eq!(col.min, Some(Bound::Str("QU".into())));
+    assert_eq!(col.max, Some(Bound::Str("QX".into())));
+    assert_eq!(col.distinct_estimate, 2);
+    assert_eq!(col.max_width, 2);
+    assert!(!col.nullable);
+    let col = &schema.columns[1];
+    assert_eq!(col.min, Some(Bound::UInt(2012060000)));
+    assert_eq!(col.max, Some(Bound::UInt(2013060000)));
+    assert!(schema.columns[4].nullable);
+}
+
+#[test]
+fn test_parse_column() {
+    use sleek_csv::column::{ColumnErrorKind, ParseError};
+
+    let chunk = r#""COL_1","COL_2","COL_3"
+"QU",0x10,77.00
+"QU",2012060000,
+"QU",-3,5.5
+"#;
+
+    let mut arena = ByteRecordArena::new();
+    let mut reader = Reader::new(true, b',');
+    reader.fill_arena(chunk.as_bytes(), &mut arena).unwrap();
+
+    assert_eq!(arena.parse_column::<i64>(1).unwrap(), [16, 2012060000, -3]);
+    assert_eq!(
+        arena.parse_column::<Option<f64>>(2).unwrap(),
+        [Some(77.0), None, Some(5.5)]
+    );
+    let err = arena.parse_column::<u32>(1).unwrap_err();
+    assert_eq!((err.row, err.column), (2, 1));
+    assert_eq!(err.kind, ColumnErrorKind::Parse(ParseError::InvalidDigit));
+    let err = arena.parse_column::<String>(3).unwrap_err();
+    assert_eq!(err.kind, ColumnErrorKind::MissingField);
+}
+
+#[test]
+fn test_unquoted_fast_path() {
+    // The fast path is disabled for readers built from csv_core, so they're used as the reference.
+    let input = "\u{feff}\"COL_1\",COL_2,COL_3\r\nQU,2012060000,77.00\r\n\r\nQU,,3.00\n\nQU,\"a\"\"b\",7\rQX,\"漢\r\n漢\",x\r\n,,\r\nQU,y\"z,5\nQU,2012060000,4";
+    let input = input.as_bytes();
+
+    let read_all = |mut reader: Reader, chunk_size: usize| {
+        let mut arena = ByteRecordArena::new();
+        let mut output = Vec::new();
+        for chunk in input.chunks(chunk_size) {
+            let mut next_arena = ByteRecordArena::new();
+            arena.migrate_partial(&mut next_arena);
+            arena = next_arena;
+            reader.fill_arena(chunk, &mut arena).unwrap();
+            output.push(format!("{:?} {:?}", arena.start_pos(), arena));
+        }
+        arena.complete_partial();
+        output.push(format!("{:?} {:?}", arena.headers(), arena));
+        output
+    };
+
+    for chunk_size in 1..=input.len() {
+        let fast = read_all(Reader::new(true, b','), chunk_size);
+        let core = read_all(
+            Reader::from_core(csv_core::ReaderBuilder::new().build(), true),
+            chunk_size,
+        );
+        assert_eq!(fast, core, "chunk size {}", chunk_size);
+    }
+}
+
+#[test]
+fn test_arena_pool() {
+    use sleek_csv::ArenaPool;
+
+    let chunk = r#""COL_1","COL_2","COL_3"
+"QU","2012060000",77.00
+"QU","2012060000",3.00
+"#;
+
+    let mut pool = ArenaPool::new(1 << 20);
+    let mut reader = Reader::new(true, b',');
+
+    let mut arena_a = pool.get();
+    let mut arena_b = pool.get();
+    reader.fill_arena(chunk.as_bytes(), &mut arena_a).unwrap();
+    arena_a.migrate_partial(&mut arena_b);
+    reader.fill_arena(chunk.as_bytes(), &mut arena_b).unwrap();
+    assert!(arena_a.headers().is_some());
+    pool.put(arena_a);
+    pool.put(arena_b);
+
+    assert_eq!(pool.len(), 2);
+    let marks = pool.high_water_marks();
+    assert_eq!(marks.in_flight, 2);
+    assert_eq!(marks.record_ends, 3);
+    assert!(pool.pooled_bytes() > 0);
+
+    let arena = pool.get();
+    assert!(arena.is_pristine());
+    assert_eq!(pool.len(), 1);
+
+    // Arenas that don't fit in the pool are dropped
+    let mut small_pool = ArenaPool::new(0);
+    small_pool.put(arena);
+    assert!(small_pool.is_empty());
+}
+
+#[test]
+fn test_read_limits() {
+    use sleek_csv::reader::ReadError;
+
+    let chunk = "a,b,c\r\nd,eeeeeeee,f\r\n\"g\",h,i,j\r\n";
+
+    let mut arena = ByteRecordArena::new();
+    let mut reader = Reader::new(false, b',');
+    reader.set_max_field_size(Some(4));
+    assert_eq!(
+        reader
+            .fill_arena(chunk.as_bytes(), &mut arena)
+            .unwrap_err()
+            .error,
+        ReadError::FieldTooLarge {
+            row_num: 1,
+            field_num: 1,
+            max_field_size: 4
+        }
+    );
+
+    let mut arena = ByteRecordArena::new();
+    let mut reader = Reader::new(false, b',');
+    reader.set_max_fields_per_record(Some(3));
+    assert_eq!(
+        reader
+            .fill_arena(chunk.as_bytes(), &mut arena)
+            .unwrap_err()
+            .error,
+        ReadError::TooManyFields {
+            row_num: 2,
+            max_fields_per_record: 3
+        }
+    );
+
+    let mut arena = ByteRecordArena::new();
+    let mut reader = Reader::new(false, b',');
+    reader.set_arena_byte_budget(Some(8));
+    assert_eq!(
+        reader
+            .fill_arena(chunk.as_bytes(), &mut arena)
+            .unwrap_err()
+            .error,
+        ReadError::ArenaBudgetExceeded {
+            row_num: 1,
+            arena_byte_budget: 8
+        }
+    );
+}
+
+#[test]
+fn test_arena_target_size() {
+    let chunk = "a,b,c\nd,e,f\ng,h,i\nj,k,l\n";
+
+    let mut reader = Reader::new(false, b',');
+    reader.set_arena_target_size(Some(5));
+    let mut input = chunk.as_bytes();
+    let mut counts = Vec::new();
+    while !input.is_empty() {
+        let mut arena = ByteRecordArena::new();
+        let outcome = reader.fill_arena(input, &mut arena).unwrap();
+        input = &input[outcome.bytes_consumed..];
+        counts.push(arena.record_count());
+        assert_eq!(
+            arena.start_pos().unwrap().record,
+            2 * (counts.len() as u64 - 1)
+        );
+    }
+    assert_eq!(counts, [2, 2]);
+}
+
+#[test]
+fn test_fill_outcome() {
+    use sleek_csv::reader::{ReadError, StopReason};
+
+    let chunk = "a,b,c\nd,e\nf,g,h\ni,\"j";
+
+    let mut arena = ByteRecordArena::new();
+    let mut reader = Reader::new(false, b',');
+    let err = reader.fill_arena(chunk.as_bytes(), &mut arena).unwrap_err();
+    assert!(matches!(err.error, ReadError::WrongColCount(_)));
+    assert_eq!(err.outcome.stop_reason, StopReason::Error);
+    assert_eq!(err.outcome.bytes_consumed, 10);
+    assert_eq!(err.outcome.records_added, 2);
+
+    // Resume after the offending record
+    let mut next_arena = ByteRecordArena::new();
+    let outcome = reader
+        .fill_arena(
+            &chunk.as_bytes()[err.outcome.bytes_consumed..],
+            &mut next_arena,
+        )
+        .unwrap();
+    assert_eq!(outcome.stop_reason, StopReason::InputExhausted);
+    assert_eq!(outcome.bytes_consumed, chunk.len() - 10);
+    assert_eq!(outcome.records_added, 1);
+    assert!(outcome.partial_record);
+    assert!(outcome.partial_field);
+}
+
+#[test]
+fn test_arena_record_limit() {
+    use sleek_csv::reader::StopReason;
+
+    let chunk_a = "\"COL_1\",\"COL_2\"\na,1\nb,2\nc,3\n";
+    let chunk_b = "d,4\ne,5\nf,";
+
+    let mut reader = Reader::new(true, b',');
+    reader.set_arena_record_limit(Some(2));
+    let mut batches = Vec::new();
+    let mut arena = ByteRecordArena::new();
+    for chunk in &[chunk_a, chunk_b] {
+        let mut input = chunk.as_bytes();
+        loop {
+            let outcome = reader.fill_arena(input, &mut arena).unwrap();
+            input = &input[outcome.bytes_consumed..];
+            if outcome.stop_reason == StopReason::InputExhausted {
+                break;
+            }
+            assert_eq!(outcome.stop_reason, StopReason::RecordLimit);
+            let mut next_arena = ByteRecordArena::new();
+            arena.migrate_partial(&mut next_arena);
+            batches.push(arena.record_count());
+            arena = next_arena;
+        }
+    }
+    arena.complete_partial();
+    batches.push(arena.record_count());
+    assert_eq!(batches, [2, 2, 2]);
+
+    // A held back trailer candidate that turns out to be data counts towards the limit
+    let mut reader = Reader::new(false, b',');
+    reader.set_arena_record_limit(Some(2));
+    reader.set_trailer_marker(Some(b"T"));
+    let mut input = &b"a,1\nT,2\nb,3\nc,4\n"[..];
+    let mut batches = Vec::new();
+    loop {
+        let mut arena = ByteRecordArena::new();
+        let outcome = reader.fill_arena(input, &mut arena).unwrap();
+        input = &input[outcome.bytes_consumed..];
+        assert_eq!(outcome.records_added, arena.record_count());
+        let stop_reason = outcome.stop_reason;
+        if stop_reason == StopReason::InputExhausted {
+            reader.finish(&mut arena).unwrap();
+        }
+        let records: Vec<Vec<u8>> = arena.iter().map(|r| r.get(0).unwrap().to_vec()).collect();
+        batches.push(records);
+        if stop_reason == StopReason::InputExhausted {
+            break;
+        }
+        assert_eq!(stop_reason, StopReason::RecordLimit);
+    }
+    assert_eq!(
+        batches,
+        [
+            vec![b"a".to_vec(), b"T".to_vec()],
+            vec![b"b".to_vec(), b"c".to_vec()],
+            vec![]
+        ]
+    );
+}
+
+#[test]
+fn test_preamble_comments_and_trailer() {
+    use sleek_csv::pattern::Pattern;
+
+    let input =
+        "Vendor report\nGenerated \"2019-10-01\n\nID,NAME\n# comment\n1,a\n2,b\n#,x,y\nTOTAL,2\n";
+
+    // Every split point exercises a header marker or a comment that spans two chunks
+    for split in 0..input.len() {
+        let mut reader = Reader::new(true, b',');
+        reader.set_skip_lines(1);
+        reader.set_header_marker(Some(Pattern::new(r"ID,\w+").unwrap()));
+        reader.set_comment(Some(b'#')).unwrap();
+        reader.set_trailer_marker(Some(b"TOTAL"));
+        let mut arena = ByteRecordArena::new();
+        for chunk in &[&input[..split], &input[split..]] {
+            let outcome = reader.fill_arena(chunk.as_bytes(), &mut arena).unwrap();
+            assert_eq!(outcome.bytes_consumed, chunk.len());
+        }
+        reader.finish(&mut arena).unwrap();
+        let headers: Vec<&[u8]> = arena.headers().unwrap().iter().collect();
+        assert_eq!(headers, [&b"ID"[..], b"NAME"], "split at {}", split);
+        let records: Vec<Vec<&[u8]>> = arena.iter().map(|r| r.iter().collect()).collect();
+        assert_eq!(records, [[&b"1"[..], b"a"], [&b"2"[..], b"b"]]);
+        let trailer: Vec<&[u8]> = arena.trailer().unwrap().iter().collect();
+        assert_eq!(trailer, [&b"TOTAL"[..], b"2"]);
+        assert_eq!(arena.start_pos().unwrap().line, 4, "split at {}", split);
+        let header_start = input.find("ID,").unwrap() as u64;
+        assert_eq!(
+            arena.start_pos().unwrap().byte,
+            header_start,
+            "split at {}",
+            split
+        );
+    }
+
+    // Lines ending with `\r` only are skipped too
+    let input = "Report\rGenerated\r\nID,NAME\r1,a\r";
+    for split in 0..input.len() {
+        let mut reader = Reader::new(true, b',');
+        reader.set_skip_lines(2);
+        let mut arena = ByteRecordArena::new();
+        for chunk in &[&input[..split], &input[split..]] {
+            reader.fill_arena(chunk.as_bytes(), &mut arena).unwrap();
+        }
+        let headers: Vec<&[u8]> = arena.headers().unwrap().iter().collect();
+        assert_eq!(headers, [&b"ID"[..], b"NAME"], "split at {}", split);
+        assert_eq!(arena.record_count(), 1);
+        assert_eq!(arena.start_pos().unwrap().byte, 18, "split at {}", split);
+    }
+}
+
+#[test]
+fn test_comment_on_core_reader() {
+    let core = csv_core::ReaderBuilder::new().comment(Some(b'#')).build();
+    let mut reader = Reader::from_core(core, false);
+    assert!(reader.set_comment(Some(b'#')).is_err());
+    let mut arena = ByteRecordArena::new();
+    reader.fill_arena(b"# comment\na,b\n", &mut arena).unwrap();
+    assert_eq!(arena.record_count(), 1);
+}
+
+#[test]
+fn test_trailer_is_last_record() {
+    use sleek_csv::reader::ReadError;
+
+    let records = |arena: &ByteRecordArena| -> Vec<Vec<u8>> {
+        arena
+            .iter()
+            .map(|r| r.iter().collect::<Vec<_>>().join(&b'|'))
+            .collect()
+    };
+    let trailer = |arena: &ByteRecordArena| {
+        arena
+            .trailer()
+            .map(|r| r.iter().collect::<Vec<_>>().join(&b'|'))
+    };
+
+    // Prefix matches and mid-stream markers are data
+    let input = b"TOTALLY,1\nTOTAL,2\nx,3\nTOTAL,4\n";
+    let mut reader = Reader::new(false, b',');
+    reader.set_trailer_marker(Some(b"TOTAL"));
+    let mut arena = ByteRecordArena::new();
+    reader.fill_arena(input, &mut arena).unwrap();
+    assert_eq!(records(&arena), [&b"TOTALLY|1"[..], b"TOTAL|2", b"x|3"]);
+    assert_eq!(trailer(&arena), None);
+    reader.finish(&mut arena).unwrap();
+    assert_eq!(trailer(&arena), Some(b"TOTAL|4".to_vec()));
+
+    // A held back record is read into the next arena if more records follow
+    let mut reader = Reader::new(false, b',');
+    reader.set_trailer_marker(Some(b"TOTAL"));
+    let mut first = ByteRecordArena::new();
+    reader.fill_arena(b"a,1\nTOTAL,2\n", &mut first).unwrap();
+    let mut second = ByteRecordArena::new();
+    reader.fill_arena(b"b,3\nTOTAL,4", &mut second).unwrap();
+    reader.finish(&mut second).unwrap();
+    assert_eq!(records(&first), [&b"a|1"[..]]);
+    assert_eq!(trailer(&first), None);
+    assert_eq!(records(&second), [&b"TOTAL|2"[..], b"b|3"]);
+    assert_eq!(trailer(&second), Some(b"TOTAL|4".to_vec()));
+
+    // A held back record that isn't the trailer is checked for the column count
+    let mut reader = Reader::new(false, b',');
+    reader.set_trailer_marker(Some(b"TOTAL"));
+    let mut arena = ByteRecordArena::new();
+    reader.fill_arena(b"a,1\nTOTAL\nb,2", &mut arena).unwrap();
+    let err = reader.finish(&mut arena).unwrap_err();
+    assert!(matches!(err, ReadError::WrongColCount(_)), "{:?}", err);
+}
+
+#[test]
+fn test_header_policy() {
+    use sleek_csv::headers::HeaderCase;
+    use sleek_csv::{HeaderPolicy, Writer};
+
+    let input = "Population,,, Area\n Male ,Female,female,\n1,2,3,4\n";
+
+    let mut reader = Reader::new(true, b',');
+    reader.set_header_policy(HeaderPolicy {
+        rows: 2,
+        trim: true,
+        case: HeaderCase::Lower,
+        dedup: true,
+        blank_prefix: Some(b"column_".to_vec()),
+        ..HeaderPolicy::default()
+    });
+    let mut arena = ByteRecordArena::new();
+    reader.fill_arena(input.as_bytes(), &mut arena).unwrap();
+    let headers = arena.headers().unwrap();
+    let names: Vec<&[u8]> = headers.iter().collect();
+    assert_eq!(
+        names,
+        [
+            &b"population_male"[..],
+            b"population_female",
+            b"population_female_2",
+            b"area"
+        ]
+    );
+    assert_eq!(headers.raw_rows().len(), 2);
+    assert_eq!(arena.record_count(), 1);
+
+    let mut writer = Writer::new(false, b',');
+    let mut output = Vec::new();
+    writer.dump_arena(&mut output, &arena);
+    assert_eq!(output, input.as_bytes());
+
+    let mut reader = Reader::new(true, b',');
+    reader.set_header_policy(HeaderPolicy {
+        blank_prefix: Some(b"column_".to_vec()),
+        ..HeaderPolicy::default()
+    });
+    let mut arena = ByteRecordArena::new();
+    reader.fill_arena(b"a,,c\n1,2,3\n", &mut arena).unwrap();
+    let names: Vec<&[u8]> = arena.headers().unwrap().iter().collect();
+    assert_eq!(names, [&b"a"[..], b"column_2", b"c"]);
+
+    let mut writer = Writer::new(false, b',');
+    writer.set_normalized_headers(true);
+    writer.dump_arena(&mut output, &arena);
+    assert_eq!(output, b"a,column_2,c\n1,2,3\n");
+}
+
+#[test]
+fn test_headers_lookup() {
+    use sleek_csv::reader::ReadError;
+    use sleek_csv::Headers;
+
+    let headers = Headers::from(&["id", "Name", "name"][..]);
+    assert_eq!(headers.get(0), b"id");
+    assert_eq!(headers.get(2), b"name");
+    assert_eq!(headers.index_of("Name"), Some(1));
+    assert_eq!(headers.index_of(b"name"), Some(2));
+    assert_eq!(headers.index_of("NAME"), None);
+    assert_eq!(headers.index_of_ignore_case("NAME"), Some(1));
+    assert!(headers.contains("id"));
+    assert!(!headers.contains("ID"));
+
+    // Case folding is the same as with `HeaderCase::Lower`
+    let unicode = Headers::from(&["Straße", "ÄRZTE"][..]);
+    assert_eq!(unicode.index_of_ignore_case("ärzte"), Some(1));
+    assert_eq!(unicode.index_of_ignore_case("STRASSE"), None);
+    assert_eq!(unicode.clone(), Headers::from(&["Straße", "ÄRZTE"][..]));
+
+    let mut arena = ByteRecordArena::with_headers(headers.clone());
+    let mut reader = Reader::new(false, b',');
+    let err = reader.fill_arena(b"1,a,b\n2,c\n", &mut arena).unwrap_err();
+    assert!(matches!(err.error, ReadError::WrongColCount(_)));
+    assert_eq!(err.outcome.records_added, 2);
+    assert_eq!(arena.headers(), Some(&headers));
+}
+
+#[test]
+fn test_trim() {
+    use sleek_csv::reader::Trim;
+
+    let input = " id , name \n 1 ,\" a b \"\n2,\n";
+
+    let mut reader = Reader::new(true, b',');
+    reader.set_trim(Trim::All);
+    let mut arena = ByteRecordArena::new();
+    reader.fill_arena(input.as_bytes(), &mut arena).unwrap();
+    let names: Vec<&[u8]> = arena.headers().unwrap().iter().collect();
+    assert_eq!(names, [&b"id"[..], b"name"]);
+    let records: Vec<Vec<&[u8]>> = arena.iter().map(|r| r.iter().collect()).collect();
+    assert_eq!(records, [[&b"1"[..], b"a b"], [&b"2"[..], b""]]);
+    assert_eq!(arena.parse_column::<u32>(0).unwrap(), [1, 2]);
+
+    let mut reader = Reader::new(true, b',');
+    reader.set_trim(Trim::Headers);
+    let mut arena = ByteRecordArena::new();
+    reader.fill_arena(input.as_bytes(), &mut arena).unwrap();
+    let names: Vec<&[u8]> = arena.headers().unwrap().iter().collect();
+    assert_eq!(names, [&b"id"[..], b"name"]);
+    assert_eq!(arena.iter().next().unwrap().get(0), Some(&b" 1 "[..]));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_deserialize_trim() {
+    use sleek_csv::reader::Trim;
+    use sleek_csv::DeserializeOptions;
+
+    let mut reader = Reader::new(false, b',');
+    let mut arena = ByteRecordArena::new();
+    reader.fill_arena(b" 42 , 1.5\t,\n", &mut arena).unwrap();
+
+    let mut records = Vec::<(u32, f64, Option<u8>)>::new();
+    assert!(arena.deserialize(&mut records).is_err());
+    let options = DeserializeOptions {
+        trim: Trim::Fields,
+        ..DeserializeOptions::default()
+    };
+    arena.deserialize_with(&options, &mut records).unwrap();
+    assert_eq!(records, [(42, 1.5, None)]);
+}
+
+#[test]
+fn test_null_tokens() {
+    use sleek_csv::schema::ColumnType;
+    use sleek_csv::{ColumnOptions, NullTokens, SchemaInferrer, Writer};
+
+    let mut reader = Reader::new(false, b',');
+    let mut arena = ByteRecordArena::new();
+    reader
+        .fill_arena(b"1,NULL\n\\N,2\nNA,\n", &mut arena)
+        .unwrap();
+    let nulls = NullTokens::new(&["NULL", "\\N", "NA"]);
+
+    assert!(arena.parse_column::<Option<u8>>(0).is_err());
+    assert_eq!(
+        arena.parse_column_with::<Option<u8>>(
+            0,
+            &ColumnOptions {
+                null_tokens: nulls.clone(),
+                ..ColumnOptions::default()
+            }
+        ),
+        Ok(vec![Some(1), None, None])
+    );
+
+    let mut inferrer = SchemaInferrer::new();
+    inferrer.set_null_tokens(nulls);
+    inferrer.scan(&arena);
+    let schema = inferrer.schema();
+    assert_eq!(schema.columns[0].column_type, ColumnType::UInt64);
+    assert!(schema.columns[0].nullable);
+    assert_eq!(schema.columns[1].column_type, ColumnType::UInt64);
+
+    let mut writer = Writer::new(false, b',');
+    writer.set_null_value(Some(b"NULL".to_vec()));
+    let mut output = Vec::new();
+    writer.dump_arena(&mut output, &arena);
+    assert_eq!(output, b"1,NULL\n\\N,2\nNA,NULL\n");
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_deserialize_null_tokens() {
+    use sleek_csv::{DeserializeOptions, NullTokens};
+
+    let mut reader = Reader::new(false, b',');
+    let mut arena = ByteRecordArena::new();
+    reader.fill_arena(b"#N/A,-,3\n", &mut arena).unwrap();
+
+    let options = DeserializeOptions {
+        null_tokens: NullTokens::new(&["#N/A", "-"]),
+        ..DeserializeOptions::default()
+    };
+    let mut records = Vec::<(Option<u8>, Option<String>, Option<u8>)>::new();
+    arena.deserialize_with(&options, &mut records).unwrap();
+    assert_eq!(records, [(None, None, Some(3))]);
+}
+
+#[test]
+fn test_number_format() {
+    use sleek_csv::{ColumnOptions, NumberFormat};
+
+    let mut reader = Reader::new(false, b';');
+    let mut arena = ByteRecordArena::new();
+    reader
+        .fill_arena("1.234,56 €;-7\n0,5 €;1.000\n".as_bytes(), &mut arena)
+        .unwrap();
+    let options = ColumnOptions {
+        number_format: NumberFormat {
+            decimal_separator: b',',
+            grouping_separator: Some(b'.'),
+            prefixes: Vec::new(),
+            suffixes: vec![" €".as_bytes().to_vec()],
+        },
+        ..ColumnOptions::default()
+    };
+    assert_eq!(
+        arena.parse_column_with::<f64>(0, &options),
+        Ok(vec![1234.56, 0.5])
+    );
+    assert_eq!(
+        arena.parse_column_with::<i32>(1, &options),
+        Ok(vec![-7, 1000])
+    );
+    assert!(arena.parse_column::<f64>(0).is_err());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_deserialize_number_format() {
+    use sleek_csv::{DeserializeOptions, NumberFormat};
+
+    let mut reader = Reader::new(false, b',');
+    let mut arena = ByteRecordArena::new();
+    reader
+        .fill_arena(b"\"$1,234.50\",\"-$2,000\",abc\n", &mut arena)
+        .unwrap();
+    let options = DeserializeOptions {
+        number_format: NumberFormat {
+            grouping_separator: Some(b','),
+            prefixes: vec![b"$".to_vec()],
+            ..NumberFormat::default()
+        },
+        ..DeserializeOptions::default()
+    };
+    let mut records = Vec::<(f64, i64, String)>::new();
+    arena.deserialize_with(&options, &mut records).unwrap();
+    assert_eq!(records, [(1234.5, -2000, "abc".to_string())]);
+}
+
+#[cfg(feature = "chrono")]
+#[test]
+fn test_parse_datetime_column() {
+    use chrono::NaiveDate;
+
+    let mut reader = Reader::new(false, b',');
+    let mut arena = ByteRecordArena::new();
+    reader
+        .fill_arena(b"2019/10/01 1:11:11,a\n,b\n", &mut arena)
+        .unwrap();
+    let dates = arena
+        .parse_datetime_column::<Option<chrono::NaiveDateTime>>(0, "%Y/%m/%d %H:%M:%S")
+        .unwrap();
+    let date = NaiveDate::from_ymd_opt(2019, 10, 1).unwrap();
+    assert_eq!(dates, [date.and_hms_opt(1, 11, 11), None]);
+    assert!(arena.parse_column::<chrono::NaiveDateTime>(0).is_err());
+}
+
+#[cfg(all(feature = "chrono", feature = "serde"))]
+#[test]
+fn test_deserialize_datetime() {
+    use chrono::{NaiveDate, NaiveDateTime};
+    use sleek_csv::datetime::DateTimeFormats;
+    use sleek_csv::DeserializeOptions;
+
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    struct Record(
+        String,
+        #[serde(with = "sleek_csv::datetime::naive_date_time")] NaiveDateTime,
+        #[serde(with = "sleek_csv::datetime::naive_date::option")] Option<NaiveDate>,
+    );
+
+    let mut reader = Reader::new(false, b',');
+    let mut arena = ByteRecordArena::new();
+    reader
+        .fill_arena(
+            b"A000,2019/10/01 1:11:11,2019-10-09\nA001,2019/10/01 1:11:12,\n",
+            &mut arena,
+        )
+        .unwrap();
+
+    let mut formats = DateTimeFormats::new();
+    formats.set_column(1, "%Y/%m/%d %H:%M:%S");
+    let options = DeserializeOptions {
+        datetime_formats: formats,
+        ..DeserializeOptions::default()
+    };
+    let mut records = Vec::<Record>::new();
+    arena.deserialize_with(&options, &mut records).unwrap();
+    let date = NaiveDate::from_ymd_opt(2019, 10, 1).unwrap();
+    assert_eq!(
+        records[0],
+        Record(
+            "A000".into(),
+            date.and_hms_opt(1, 11, 11).unwrap(),
+            NaiveDate::from_ymd_opt(2019, 10, 9)
+        )
+    );
+    assert_eq!(records[1].2, None);
+
+    let mut records = Vec::<Record>::new();
+    let err = arena.deserialize(&mut records).unwrap_err();
+    assert!(
+        err.to_string().contains("ParseDateTime { field: 1"),
+        "{}",
+        err
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_deserialize_match_headers() {
+    use sleek_csv::reader::Trim;
+    use sleek_csv::DeserializeOptions;
+
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    struct Row {
+        name: String,
+        qty: String,
+    }
+
+    let mut reader = Reader::new(true, b',');
+    let mut arena = ByteRecordArena::new();
+    reader
+        .fill_arena(b"qty,name\n3,apple\n", &mut arena)
+        .unwrap();
+
+    // By default the fields are deserialized by position
+    let mut rows = Vec::<Row>::new();
+    arena.deserialize(&mut rows).unwrap();
+    assert_eq!(
+        rows,
+        [Row {
+            name: "3".into(),
+            qty: "apple".into()
+        }]
+    );
+
+    let options = DeserializeOptions {
+        match_headers: true,
+        ..DeserializeOptions::default()
+    };
+    let mut rows = Vec::<Row>::new();
+    arena.deserialize_with(&options, &mut rows).unwrap();
+    assert_eq!(
+        rows,
+        [Row {
+            name: "apple".into(),
+            qty: "3".into()
+        }]
+    );
+
+    // The headers are matched after trimming, without changing the arena
+    let mut arena = ByteRecordArena::new();
+    Reader::new(true, b',')
+        .fill_arena(b" qty ,name\t\n3,apple\n", &mut arena)
+        .unwrap();
+    let options = DeserializeOptions {
+        match_headers: true,
+        trim: Trim::Headers,
+        ..DeserializeOptions::default()
+    };
+    let mut rows = Vec::<Row>::new();
+    arena.deserialize_with(&options, &mut rows).unwrap();
+    assert_eq!(rows[0].name, "apple");
+    assert_eq!(arena.headers().unwrap().get(0), b" qty ");
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_deserialize_enum_rows() {
+    use sleek_csv::reader::ReadError;
+    use sleek_csv::DeserializeOptions;
+
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    #[serde(rename_all = "snake_case")]
+    enum Event {
+        Login(String, u64),
+        Logout { user: String },
+        Ping(u8),
+        Idle,
+    }
+
+    // The rows are padded to the same width, the payload ignoring the trailing fields.
+    let input = b"login,alice,1571000000\nlogout,alice,\nping,3,\nidle,,\n";
+    let mut reader = Reader::new(false, b',');
+    let mut arena = ByteRecordArena::new();
+    reader.fill_arena(input, &mut arena).unwrap();
+    let mut events = Vec::<Event>::new();
+    arena.deserialize(&mut events).unwrap();
+    assert_eq!(
+        events,
+        [
+            Event::Login("alice".into(), 1571000000),
+            Event::Logout {
+                user: "alice".into()
+            },
+            Event::Ping(3),
+            Event::Idle,
+        ]
+    );
+
+    // Without padding, the reader must allow records of different widths
+    let input = b"login,alice,1571000000\nlogout,alice\nping,3\nidle\n";
+    let mut arena = ByteRecordArena::new();
+    let err = Reader::new(false, b',')
+        .fill_arena(input, &mut arena)
+        .unwrap_err();
+    assert!(matches!(err.error, ReadError::WrongColCount(_)));
+    let mut reader = Reader::new(false, b',');
+    reader.set_flexible(true);
+    let mut arena = ByteRecordArena::new();
+    reader.fill_arena(input, &mut arena).unwrap();
+    let mut unpadded = Vec::<Event>::new();
+    arena.deserialize(&mut unpadded).unwrap();
+    assert_eq!(unpadded, events);
+
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    #[serde(rename_all = "snake_case")]
+    enum Change {
+        Move { to: String, from: String },
+        Delete { from: String },
+    }
+
+    let input = b"kind,from,to\nmove,a.txt,b.txt\ndelete,c.txt,\n";
+    let mut reader = Reader::new(true, b',');
+    let mut arena = ByteRecordArena::new();
+    reader.fill_arena(input, &mut arena).unwrap();
+    let options = DeserializeOptions {
+        match_headers: true,
+        ..DeserializeOptions::default()
+    };
+    let mut changes = Vec::<Change>::new();
+    arena.deserialize_with(&options, &mut changes).unwrap();
+    assert_eq!(
+        changes,
+        [
+            Change::Move {
+                to: "b.txt".into(),
+                from: "a.txt".into()
+            },
+            Change::Delete {
+                from: "c.txt".into()
+            },
+        ]
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_deserialize_internally_tagged() {
+    use sleek_csv::DeserializeOptions;
+
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    #[serde(tag = "type", rename_all = "snake_case")]
+    enum Shape {
+        Circle { radius: f64 },
+        Rect { width: f64, height: Option<f64> },
+    }
+
+    let input = b"width,type,radius,height\n,circle,1.5,\n2,rect,,3\n";
+    let mut reader = Reader::new(true, b',');
+    let mut arena = ByteRecordArena::new();
+    reader.fill_arena(input, &mut arena).unwrap();
+    let options = DeserializeOptions {
+        match_headers: true,
+        ..DeserializeOptions::default()
+    };
+    let mut shapes = Vec::<Shape>::new();
+    arena.deserialize_with(&options, &mut shapes).unwrap();
+    assert_eq!(
+        shapes,
+        [
+            Shape::Circle { radius: 1.5 },
+            Shape::Rect {
+                width: 2.0,
+                height: Some(3.0)
+            },
+        ]
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_deserialize_nested_and_flatten() {
+    use std::collections::HashMap;
+
+    use sleek_csv::{DeserializeOptions, NullTokens};
+
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    struct Address {
+        city: String,
+        zip: u32,
+    }
+
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    struct Customer {
+        id: u64,
+        billing: Address,
+        shipping: Option<Address>,
+        #[serde(flatten)]
+        rest: HashMap<String, String>,
+    }
+
+    let input = b"id,billing.city,billing.zip,note,shipping.city,shipping.zip,score\n\
+        7,Oslo,150,vip,Bergen,5003,12\n";
+    let mut reader = Reader::new(true, b',');
+    let mut arena = ByteRecordArena::new();
+    reader.fill_arena(input, &mut arena).unwrap();
+    let options = DeserializeOptions {
+        match_headers: true,
+        nested_separator: Some(b".".to_vec()),
+        buffer_as_strings: true,
+        ..DeserializeOptions::default()
+    };
+    let mut customers = Vec::<Customer>::new();
+    arena.deserialize_with(&options, &mut customers).unwrap();
+    let rest: HashMap<String, String> = [("note", "vip"), ("score", "12")]
+        .iter()
+        .map(|&(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    assert_eq!(
+        customers,
+        [Customer {
+            id: 7,
+            billing: Address {
+                city: "Oslo".into(),
+                zip: 150
+            },
+            shipping: Some(Address {
+                city: "Bergen".into(),
+                zip: 5003
+            }),
+            rest,
+        }]
+    );
+
+    // With the default options, the numeric leftover field can't be collected as a string
+    let options = DeserializeOptions {
+        buffer_as_strings: false,
+        ..options
+    };
+    let mut customers = Vec::<Customer>::new();
+    let err = arena
+        .deserialize_with(&options, &mut customers)
+        .unwrap_err();
+    assert!(err.to_string().contains("expected a string"), "{}", err);
+
+    // Without `buffer_as_strings`, the leftover fields are typed
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    struct Row {
+        id: u64,
+        #[serde(flatten)]
+        billing: Address,
+    }
+    let input = b"id,city,zip\n7,Oslo,150\n";
+    let mut arena = ByteRecordArena::new();
+    Reader::new(true, b',')
+        .fill_arena(input, &mut arena)
+        .unwrap();
+    let options = DeserializeOptions {
+        match_headers: true,
+        ..DeserializeOptions::default()
+    };
+    let mut rows = Vec::<Row>::new();
+    arena.deserialize_with(&options, &mut rows).unwrap();
+    assert_eq!(rows[0].billing.zip, 150);
+
+    // Empty fields and null tokens are buffered as `None`
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    struct Inner {
+        b: Option<u32>,
+        c: Option<String>,
+    }
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    struct Outer {
+        a: u32,
+        #[serde(flatten)]
+        inner: Inner,
+    }
+    let mut arena = ByteRecordArena::new();
+    Reader::new(true, b',')
+        .fill_arena(b"a,b,c\n1,,NA\n2,3,x\n", &mut arena)
+        .unwrap();
+    let options = DeserializeOptions {
+        match_headers: true,
+        null_tokens: NullTokens::new(&["NA"]),
+        ..DeserializeOptions::default()
+    };
+    let mut rows = Vec::<Outer>::new();
+    arena.deserialize_with(&options, &mut rows).unwrap();
+    assert_eq!(
+        rows,
+        [
+            Outer {
+                a: 1,
+                inner: Inner { b: None, c: None }
+            },
+            Outer {
+                a: 2,
+                inner: Inner {
+                    b: Some(3),
+                    c: Some("x".into())
+                }
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_bool_formats() {
+    use sleek_csv::schema::ColumnType;
+    use sleek_csv::{BoolFormat, BoolFormats, ColumnOptions, SchemaInferrer};
+
+    let mut reader = Reader::new(true, b',');
+    let mut arena = ByteRecordArena::new();
+    reader
+        .fill_arena(
+            b"active,legacy,count\nYes,J,1\nno,N,0\nON,J,2\n",
+            &mut arena,
+        )
+        .unwrap();
+    let mut bools = BoolFormats::new(BoolFormat::lenient());
+    bools.set_column("legacy", BoolFormat::new(&["J"], &["N"], false));
+    let options = ColumnOptions {
+        bool_formats: bools.clone(),
+        ..ColumnOptions::default()
+    };
+    assert_eq!(
+        arena.parse_column_with::<bool>(0, &options),
+        Ok(vec![true, false, true])
+    );
+    assert_eq!(
+        arena.parse_column_with::<bool>(1, &options),
+        Ok(vec![true, false, true])
+    );
+    assert!(arena.parse_column::<bool>(0).is_err());
+
+    let mut inferrer = SchemaInferrer::new();
+    inferrer.set_bool_formats(bools);
+    inferrer.scan(&arena);
+    let schema = inferrer.schema();
+    assert_eq!(schema.columns[0].column_type, ColumnType::Bool);
+    assert_eq!(schema.columns[1].column_type, ColumnType::Bool);
+    assert_eq!(schema.columns[2].column_type, ColumnType::UInt64);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_deserialize_bool_formats() {
+    use sleek_csv::{BoolFormat, BoolFormats, DeserializeOptions};
+
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    struct Row {
+        legacy: bool,
+        active: bool,
+    }
+
+    let mut reader = Reader::new(true, b',');
+    let mut arena = ByteRecordArena::new();
+    reader
+        .fill_arena(b"active,legacy\nY,J\nf,N\n", &mut arena)
+        .unwrap();
+    let mut bools = BoolFormats::new(BoolFormat::lenient());
+    bools.set_column("legacy", BoolFormat::new(&["J"], &["N"], false));
+    let options = DeserializeOptions {
+        match_headers: true,
+        bool_formats: bools,
+        ..DeserializeOptions::default()
+    };
+    let mut rows = Vec::<Row>::new();
+    arena.deserialize_with(&options, &mut rows).unwrap();
+    assert_eq!(
+        rows,
+        [
+            Row {
+                legacy: true,
+                active: true
+            },
+            Row {
+                legacy: false,
+                active: false
+            }
+        ]
+    );
+
+    let mut rows = Vec::<Row>::new();
+    let err = arena.deserialize(&mut rows).unwrap_err();
+    assert!(err.to_string().contains("ParseBool(\"Y\")"), "{}", err);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_deserialize_single_records() {
+    use sleek_csv::DeserializeOptions;
+
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    struct Row<'a> {
+        name: &'a str,
+        qty: u32,
+    }
+
+    let mut reader = Reader::new(true, b',');
+    let mut arena = ByteRecordArena::new();
+    reader
+        .fill_arena(b"qty,name\n3,apple\nx,pear\n5,plum\n", &mut arena)
+        .unwrap();
+
+    let options = DeserializeOptions {
+        match_headers: true,
+        ..DeserializeOptions::default()
+    };
+    let first = arena.iter().next().unwrap();
+    let row: Row = first.deserialize_with(arena.headers(), &options).unwrap();
+    assert_eq!(
+        row,
+        Row {
+            name: "apple",
+            qty: 3
+        }
+    );
+    let tuple: (u32, String) = first.deserialize(None).unwrap();
+    assert_eq!(tuple, (3, "apple".to_string()));
+
+    let mut rows = arena.deserialize_iter_with::<Row>(&options);
+    assert_eq!(rows.next().unwrap().unwrap().name, "apple");
+    let err = rows.next().unwrap().unwrap_err();
+    assert!(err.to_string().contains("at index 1"), "{}", err);
+    assert_eq!(rows.next().unwrap().unwrap().qty, 5);
+    assert!(rows.next().is_none());
+
+    let valid: Vec<Row> = arena
+        .deserialize_iter_with(&options)
+        .filter_map(Result::ok)
+        .collect();
+    assert_eq!(valid.len(), 2);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_deserialize_seed() {
+    use std::collections::HashMap;
+    use std::fmt;
+
+    use serde::de::{self, DeserializeSeed, Deserializer, SeqAccess, Visitor};
+
+    #[derive(Default)]
+    struct Interner {
+        ids: HashMap<String, u32>,
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Row {
+        city: u32,
+        count: u64,
+    }
+
+    struct RowVisitor<'c>(&'c mut Interner);
+
+    impl<'c, 'de> Visitor<'de> for RowVisitor<'c> {
+        type Value = Row;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a city and a count")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Row, A::Error> {
+            let city: &str = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+            let count = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+            let next_id = self.0.ids.len() as u32;
+            let city = *self.0.ids.entry(city.to_string()).or_insert(next_id);
+            Ok(Row { city, count })
+        }
+    }
+
+    impl<'de> DeserializeSeed<'de> for &mut Interner {
+        type Value = Row;
+
+        fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Row, D::Error> {
+            deserializer.deserialize_tuple(2, RowVisitor(self))
+        }
+    }
+
+    let mut reader = Reader::new(false, b',');
+    let mut arena = ByteRecordArena::new();
+    reader
+        .fill_arena(b"Oslo,3\nBergen,5\nOslo,7\n", &mut arena)
+        .unwrap();
+    let mut interner = Interner::default();
+    let mut rows = Vec::new();
+    assert_eq!(arena.deserialize_seed(&mut interner, &mut rows).unwrap(), 3);
+    assert_eq!(
+        rows,
+        [
+            Row { city: 0, count: 3 },
+            Row { city: 1, count: 5 },
+            Row { city: 0, count: 7 },
+        ]
+    );
+    assert_eq!(interner.ids.len(), 2);
+}
+
+#[test]
+fn test_converters() {
+    use std::borrow::Cow;
+
+    use sleek_csv::Converters;
+
+    let mut reader = Reader::new(true, b',');
+    let mut arena = ByteRecordArena::new();
+    reader
+        .fill_arena(
+            b"name,phone,cm\nAda,+47 22 33,180\nBob,22-44,x\n",
+            &mut arena,
+        )
+        .unwrap();
+
+    let mut converters = Converters::new();
+    converters.set_named("phone", |field: &[u8]| -> Result<Cow<[u8]>, String> {
+        Ok(field.iter().copied().filter(u8::is_ascii_digit).collect())
+    });
+    converters.set_column(0, |field: &[u8]| -> Result<Cow<[u8]>, String> {
+        Ok(Cow::Borrowed(field))
+    });
+    let mapped = arena.map_columns(&converters).unwrap();
+    assert_eq!(mapped.headers(), arena.headers());
+    let phones: Vec<&[u8]> = mapped.iter().map(|r| r.get(1).unwrap()).collect();
+    assert_eq!(phones, [&b"472233"[..], b"2244"]);
+    assert_eq!(mapped.iter().next().unwrap().get(0), Some(&b"Ada"[..]));
+
+    converters.set_named("cm", |field: &[u8]| match field {
+        b"x" => Err("unknown height"),
+        _ => Ok(Cow::Borrowed(field)),
+    });
+    let err = arena.map_columns(&converters).unwrap_err();
+    assert_eq!((err.row, err.column), (1, 2));
+    assert_eq!(err.message, "unknown height");
+
+    let meters = arena
+        .map_column(2, |field| {
+            std::str::from_utf8(field)
+                .unwrap()
+                .parse::<f64>()
+                .map(|cm| cm / 100.0)
+        })
+        .unwrap_err();
+    assert_eq!(meters.row, 1);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_deserialize_converters() {
+    use std::borrow::Cow;
+
+    use sleek_csv::{Converters, DeserializeOptions};
+
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    struct Row<'a> {
+        name: &'a str,
+        phone: String,
+    }
+
+    let mut reader = Reader::new(true, b',');
+    let mut arena = ByteRecordArena::new();
+    reader
+        .fill_arena(b"name,phone\nAda,+47 22 33\n", &mut arena)
+        .unwrap();
+    let mut converters = Converters::new();
+    converters.set_named("phone", |field: &[u8]| -> Result<Cow<[u8]>, String> {
+        Ok(field.iter().copied().filter(u8::is_ascii_digit).collect())
+    });
+    let options = DeserializeOptions {
+        converters,
+        ..DeserializeOptions::default()
+    };
+    let mut rows = Vec::<Row>::new();
+    arena.deserialize_with(&options, &mut rows).unwrap();
+    assert_eq!(
+        rows,
+        [Row {
+            name: "Ada",
+            phone: "472233".into()
+        }]
+    );
+}
+
+#[test]
+fn test_rows_as_values() {
+    use sleek_csv::schema::ColumnType;
+    use sleek_csv::value::row_to_map;
+    use sleek_csv::{BoolFormat, BoolFormats, SchemaInferrer, Value};
+
+    let mut reader = Reader::new(true, b',');
+    let mut arena = ByteRecordArena::new();
+    reader
+        .fill_arena(
+            b"id,name,score,ok\n1,Ada,2.5,true\n-2,,3,false\n",
+            &mut arena,
+        )
+        .unwrap();
+
+    let rows: Vec<Vec<Value>> = arena.rows_as_values().collect();
+    assert_eq!(
+        rows,
+        [
+            vec![
+                Value::UInt(1),
+                Value::Str("Ada"),
+                Value::Float(2.5),
+                Value::Bool(true)
+            ],
+            vec![
+                Value::Int(-2),
+                Value::Null,
+                Value::UInt(3),
+                Value::Bool(false)
+            ],
+        ]
+    );
+
+    let mut inferrer = SchemaInferrer::new();
+    inferrer.scan(&arena);
+    let schema = inferrer.schema();
+    let rows: Vec<Vec<Value>> = arena.rows_as_values_with(&schema).collect();
+    assert_eq!(rows[0][0], Value::Int(1));
+    assert_eq!(rows[1][2], Value::Float(3.0));
+
+    let map = row_to_map(arena.headers().unwrap(), &rows[0]).unwrap();
+    assert_eq!(map["name"], Value::Str("Ada"));
+    assert_eq!(map.len(), 4);
+
+    // The columns are parsed with the boolean formats they were inferred with
+    let mut arena = ByteRecordArena::new();
+    Reader::new(true, b',')
+        .fill_arena(b"id,active\n1,Y\n2,N\n", &mut arena)
+        .unwrap();
+    let mut inferrer = SchemaInferrer::new();
+    inferrer.set_bool_formats(BoolFormats::new(BoolFormat::new(&["Y"], &["N"], false)));
+    inferrer.scan(&arena);
+    let schema = inferrer.schema();
+    assert_eq!(schema.columns[1].column_type, ColumnType::Bool);
+    let active: Vec<Value> = arena
+        .rows_as_values_with(&schema)
+        .map(|row| row[1])
+        .collect();
+    assert_eq!(active, [Value::Bool(true), Value::Bool(false)]);
+}
+
+#[cfg(feature = "serde_json")]
+#[test]
+fn test_rows_as_json() {
+    use sleek_csv::value::row_to_json;
+
+    let mut reader = Reader::new(true, b',');
+    let mut arena = ByteRecordArena::new();
+    reader
+        .fill_arena(b"id,name,score\n1,Ada,2.5\n", &mut arena)
+        .unwrap();
+    let row = arena.rows_as_values().next().unwrap();
+    assert_eq!(
+        row_to_json(arena.headers(), &row),
+        serde_json::json!({"id": 1, "name": "Ada", "score": 2.5})
+    );
+    assert_eq!(row_to_json(None, &row), serde_json::json!([1, "Ada", 2.5]));
+}
+
+#[test]
+fn test_write_ndjson() {
+    use sleek_csv::{Headers, NdjsonWriter};
+
+    let mut reader = Reader::new(true, b',');
+    let mut arena = ByteRecordArena::new();
+    reader
+        .fill_arena(b"id,name,score\n1,\"A\"\"da\",2.5\n-2,,x\xff\n", &mut arena)
+        .unwrap();
+
+    let mut writer = NdjsonWriter::new();
+    let mut out = Vec::new();
+    writer.write_arena(&mut out, &arena).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "{\"id\":\"1\",\"name\":\"A\\\"da\",\"score\":\"2.5\"}\n\
+         {\"id\":\"-2\",\"name\":\"\",\"score\":\"x\u{fffd}\"}\n"
+    );
+
+    writer.set_infer_types(true);
+    let mut out = Vec::new();
+    writer.write_arena(&mut out, &arena).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "{\"id\":1,\"name\":\"A\\\"da\",\"score\":2.5}\n\
+         {\"id\":-2,\"name\":null,\"score\":\"x\u{fffd}\"}\n"
+    );
+    assert_eq!(writer.records_written(), 4);
+
+    let mut reader = Reader::new(false, b',');
+    let mut arena = ByteRecordArena::new();
+    reader
+        .fill_arena(b"true,inf,123456789012345678901234567890\n", &mut arena)
+        .unwrap();
+    let mut out = Vec::new();
+    writer.write_arena(&mut out, &arena).unwrap();
+    // Integers too large for 64 bits are kept exact as strings
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "[true,null,\"123456789012345678901234567890\"]\n"
+    );
+
+    // Ragged rows
+    let mut arena = ByteRecordArena::with_headers(Headers::from_names(&["a", "b"]));
+    for record in [&["1", "2", "3"][..], &["4"]] {
+        for field in record {
+            arena.push_field(field.as_bytes());
+        }
+        arena.terminate_record();
+    }
+    let mut out = Vec::new();
+    NdjsonWriter::new().write_arena(&mut out, &arena).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "{\"a\":\"1\",\"b\":\"2\",\"column_3\":\"3\"}\n\
+         {\"a\":\"4\",\"b\":null}\n"
+    );
+
+    // Duplicate header names: the first column wins
+    let mut reader = Reader::new(true, b',');
+    let mut arena = ByteRecordArena::new();
+    reader
+        .fill_arena(
+            b"id,x,id
+1,2,3
+",
+            &mut arena,
+        )
+        .unwrap();
+    let mut out = Vec::new();
+    NdjsonWriter::new().write_arena(&mut out, &arena).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "{\"id\":\"1\",\"x\":\"2\"}\n"
+    );
+}
+
+#[cfg(feature = "derive")]
+#[test]
+fn test_derive_records() {
+    use sleek_csv::record::RecordError;
+    use sleek_csv::{FromRecord, ToRecord, Writer};
+
+    #[derive(Debug, PartialEq, FromRecord, ToRecord)]
+    struct Row {
+        #[csv(rename = "ID")]
+        id: u32,
+        name: String,
+        score: Option<f64>,
+        ok: bool,
+    }
+
+    let mut reader = Reader::new(true, b',');
+    let mut arena = ByteRecordArena::new();
+    reader
+        .fill_arena(
+            b"ok,name,ID,score\ntrue,Ada,1,2.5\nfalse,Bob,0x10,\n",
+            &mut arena,
+        )
+        .unwrap();
+    let rows: Vec<Row> = arena.decode_records().unwrap();
+    assert_eq!(
+        rows,
+        [
+            Row {
+                id: 1,
+                name: "Ada".to_string(),
+                score: Some(2.5),
+                ok: true
+            },
+            Row {
+                id: 16,
+                name: "Bob".to_string(),
+                score: None,
+                ok: false
+            },
+        ]
+    );
+
+    let mut output = ByteRecordArena::with_headers(Row::headers());
+    for row in &rows {
+        output.push_record(row);
+    }
+    let mut writer = Writer::new(false, b',');
+    let mut out = Vec::new();
+    writer.dump_arena(&mut out, &output);
+    assert_eq!(out, b"ID,name,score,ok\n1,Ada,2.5,true\n16,Bob,,false\n");
+    assert_eq!(output.decode_records::<Row>().unwrap(), rows);
+
+    let mut reader = Reader::new(true, b',');
+    let mut arena = ByteRecordArena::new();
+    reader
+        .fill_arena(b"ok,name,ID,score\ntrue,Ada,-1,\n", &mut arena)
+        .unwrap();
+    let err = arena.decode_records::<Row>().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Column extraction failed on row 0, column 2 (0-based, header not in count): \
+         invalid digit found in field"
+    );
+
+    let mut reader = Reader::new(true, b',');
+    let mut arena = ByteRecordArena::new();
+    reader.fill_arena(b"ID,name\n1,Ada\n", &mut arena).unwrap();
+    assert_eq!(
+        arena.decode_records::<Row>(),
+        Err(RecordError::MissingColumn("score".to_string()))
+    );
+}
+
+#[test]
+fn test_validate() {
+    use sleek_csv::pattern::Pattern;
+    use sleek_csv::validate::{Rule, ViolationKind};
+    use sleek_csv::Validator;
+
+    let mut validator = Validator::new();
+    validator.add_rule("id", Rule::Required);
+    validator.add_rule("id", Rule::Unique);
+    validator.add_rule(
+        "code",
+        Rule::Pattern(Pattern::new(r"[A-Z]{2}-\d+").unwrap()),
+    );
+    validator.add_rule("name", Rule::MaxLength(4));
+    validator.add_rule(
+        "score",
+        Rule::Range {
+            min: Some(0.0),
+            max: Some(10.0),
+        },
+    );
+    validator.add_rule("state", Rule::one_of(&["open", "closed"]));
+    validator.add_rule("owner", Rule::Required);
+
+    let mut reader = Reader::new(true, b',');
+    let mut arena = ByteRecordArena::new();
+    reader
+        .fill_arena(
+            "id,code,name,score,state\n\
+             1,FI-1,Åsa,2.5,open\n\
+             2,fi-2,Bob,,closed\n\
+             1,SE-3,Carol,11,Open\n\
+             ,,,x,\n"
+                .as_bytes(),
+            &mut arena,
+        )
+        .unwrap();
+
+    let report = validator.validate(&arena);
+    assert!(!report.is_valid());
+    assert_eq!(report.missing_columns, ["owner"]);
+    let found: Vec<_> = report
+        .violations
+        .iter()
+        .map(|v| (v.row, v.column.as_str(), v.kind.clone()))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (
+                1,
+                "code",
+                ViolationKind::NoMatch(Pattern::new(r"[A-Z]{2}-\d+").unwrap())
+            ),
+            (2, "id", ViolationKind::Duplicate { first_row: 0 }),
+            (2, "name", ViolationKind::TooLong { length: 5, max: 4 }),
+            (
+                2,
+                "score",
+                ViolationKind::OutOfRange {
+                    min: Some(0.0),
+                    max: Some(10.0)
+                }
+            ),
+            (2, "state", ViolationKind::NotAllowed),
+            (3, "id", ViolationKind::Empty),
+            (3, "score", ViolationKind::NotANumber),
+        ]
+    );
+    assert_eq!(report.violations[0].record_num, Some(1));
+    assert_eq!(
+        report.to_string().lines().take(3).collect::<Vec<_>>(),
+        [
+            "missing column 'owner'",
+            "row 1 (record 1), column 'code', value 'fi-2': doesn't match '[A-Z]{2}-\\d+'",
+            "row 2 (record 2), column 'id', value '1': duplicate of row 0",
+        ]
+    );
+}